@@ -0,0 +1,224 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use palette::white_point::D65;
+use palette::{Lab, Lch, Srgba};
+
+use super::diff::{delta_e2000, delta_e76, delta_eok};
+use super::space::Oklab;
+use crate::{write_report, Config};
+
+/// How the step analysis is written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnalysisFormat {
+    Table,
+    Json,
+}
+
+impl fmt::Display for AnalysisFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AnalysisFormat::Table => write!(f, "table"),
+            AnalysisFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for AnalysisFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "table" => Ok(AnalysisFormat::Table),
+            "json" => Ok(AnalysisFormat::Json),
+            _ => Err(format!("Unsupported analysis format `{}`", s)),
+        }
+    }
+}
+
+/// A step of the gradient in several spaces, with its differences to the
+/// previous step.
+struct Step {
+    hex: String,
+    rgb: [u8; 3],
+    lab: Lab<D65>,
+    lch: Lch<D65>,
+    /// ΔE76, ΔE2000 and the `Oklab` distance, `None` for the first step.
+    diff: Option<[f32; 3]>,
+}
+
+/// The minimum, maximum and mean of a difference over all steps.
+struct Stats {
+    min: f32,
+    max: f32,
+    mean: f32,
+}
+
+/// A summary row of the report, with its name and the statistic it shows.
+type StatsRow = (&'static str, fn(&Stats) -> f32);
+
+/// Names of the differences, in the order of `Step::diff`.
+const DIFFERENCES: [&str; 3] = ["delta_e76", "delta_e2000", "delta_eok"];
+
+fn analyze(colors: &[Srgba]) -> (Vec<Step>, Vec<Stats>) {
+    let mut steps: Vec<Step> = Vec::with_capacity(colors.len());
+    let mut previous: Option<(Lab<D65>, Oklab)> = None;
+    for color in colors {
+        let linear = color.color.into_linear();
+        let lab = Lab::<D65>::from(linear);
+        let oklab = Oklab::from_linear(linear);
+        let diff = previous.map(|(prev_lab, prev_oklab)| {
+            [
+                delta_e76(prev_lab, lab),
+                delta_e2000(prev_lab, lab),
+                delta_eok(prev_oklab, oklab),
+            ]
+        });
+        let rgb = color.color.into_format::<u8>();
+        steps.push(Step {
+            hex: format!("{:x}", rgb),
+            rgb: [rgb.red, rgb.green, rgb.blue],
+            lab,
+            lch: Lch::from(lab),
+            diff,
+        });
+        previous = Some((lab, oklab));
+    }
+
+    let stats = (0..DIFFERENCES.len())
+        .filter_map(|i| {
+            let values: Vec<f32> = steps.iter().filter_map(|s| s.diff.map(|d| d[i])).collect();
+            if values.is_empty() {
+                return None;
+            }
+            Some(Stats {
+                min: values.iter().cloned().fold(f32::MAX, f32::min),
+                max: values.iter().cloned().fold(f32::MIN, f32::max),
+                mean: values.iter().sum::<f32>() / values.len() as f32,
+            })
+        })
+        .collect();
+    (steps, stats)
+}
+
+fn table(steps: &[Step], stats: &[Stats]) -> Result<String, fmt::Error> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{:>4}  {:6}  {:11}  {:20}  {:20}  {:>6}  {:>6}  {:>6}",
+        "step", "hex", "sRGB", "Lab", "Lch", "ΔE76", "ΔE2000", "ΔEOK"
+    )?;
+    for (i, step) in steps.iter().enumerate() {
+        let rgb = format!("{},{},{}", step.rgb[0], step.rgb[1], step.rgb[2]);
+        let lab = format!("{:.1},{:.1},{:.1}", step.lab.l, step.lab.a, step.lab.b);
+        let lch = format!(
+            "{:.1},{:.1},{:.1}",
+            step.lch.l,
+            step.lch.chroma,
+            step.lch.hue.to_positive_degrees()
+        );
+        let mut line = format!(
+            "{:>4}  {:6}  {:11}  {:20}  {:20}",
+            i + 1,
+            step.hex,
+            rgb,
+            lab,
+            lch
+        );
+        if let Some(d) = step.diff {
+            write!(line, "  {:>6.2}  {:>6.2}  {:>6.4}", d[0], d[1], d[2])?;
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    if stats.len() == DIFFERENCES.len() {
+        let rows: [StatsRow; 3] = [("min", |s| s.min), ("max", |s| s.max), ("mean", |s| s.mean)];
+        out.push('\n');
+        for (name, value) in rows.iter() {
+            writeln!(
+                out,
+                "{:>69}  {:>6.2}  {:>6.2}  {:>6.4}",
+                name,
+                value(&stats[0]),
+                value(&stats[1]),
+                value(&stats[2])
+            )?;
+        }
+    }
+    Ok(out)
+}
+
+fn json(steps: &[Step], stats: &[Stats]) -> Result<String, fmt::Error> {
+    let triple = |a: f32, b: f32, c: f32| format!("[{:.4}, {:.4}, {:.4}]", a, b, c);
+
+    let mut out = String::from("{\n  \"steps\": [\n");
+    for (i, step) in steps.iter().enumerate() {
+        let diff = match step.diff {
+            Some(d) => DIFFERENCES
+                .iter()
+                .zip(d.iter())
+                .map(|(name, value)| format!("\"{}\": {:.4}", name, value))
+                .collect::<Vec<_>>()
+                .join(", "),
+            None => DIFFERENCES
+                .iter()
+                .map(|name| format!("\"{}\": null", name))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        write!(
+            out,
+            "    {{\"hex\": \"{}\", \"srgb\": [{}, {}, {}], \"lab\": {}, \"lch\": {}, {}}}",
+            step.hex,
+            step.rgb[0],
+            step.rgb[1],
+            step.rgb[2],
+            triple(step.lab.l, step.lab.a, step.lab.b),
+            triple(
+                step.lch.l,
+                step.lch.chroma,
+                step.lch.hue.to_positive_degrees()
+            ),
+            diff
+        )?;
+        out.push_str(if i + 1 < steps.len() { ",\n" } else { "\n" });
+    }
+    out.push_str("  ],\n  \"stats\": {");
+
+    let entries: Vec<String> = DIFFERENCES
+        .iter()
+        .zip(stats)
+        .map(|(name, s)| {
+            format!(
+                "\n    \"{}\": {{\"min\": {:.4}, \"max\": {:.4}, \"mean\": {:.4}}}",
+                name, s.min, s.max, s.mean
+            )
+        })
+        .collect();
+    out.push_str(&entries.join(","));
+    out.push_str(if entries.is_empty() {
+        "}\n}\n"
+    } else {
+        "\n  }\n}\n"
+    });
+    Ok(out)
+}
+
+/// Writes each step of a stepped gradient in sRGB, `Lab` and `Lch` with its
+/// ΔE76, ΔE2000 and `Oklab` distance to the previous step, followed by the
+/// minimum, maximum and mean of each difference. Uses the format set with
+/// `--analyze`, if any, writing to `--analyze-file` or stdout.
+pub(crate) fn report_steps(config: &Config, colors: &[Srgba]) -> Result<(), Box<dyn Error>> {
+    let format = match config.analyze {
+        Some(format) => format,
+        None => return Ok(()),
+    };
+    let (steps, stats) = analyze(colors);
+    let text = match format {
+        AnalysisFormat::Table => table(&steps, &stats)?,
+        AnalysisFormat::Json => json(&steps, &stats)?,
+    };
+    write_report(&text, config.analyze_file.as_deref())
+}
@@ -0,0 +1,297 @@
+use std::error::Error;
+use std::fmt;
+
+use palette::white_point::D65;
+use palette::{Hsva, Lcha, Srgb, Srgba};
+
+use crate::CliError;
+
+/// The notations colors can be written in on the command line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Notation {
+    /// `R,G,B` with components from 0 to 255.
+    Rgb,
+    /// `R,G,B` with components from 0.0 to 1.0.
+    Decimal,
+    /// `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA`.
+    Hex,
+    /// `H,S,V` with hue in degrees, saturation and value from 0 to 100.
+    Hsv,
+    /// `L,C,h` with lightness from 0 to 100, chroma from 0 to 150 and hue in
+    /// degrees.
+    Lch,
+}
+
+// Every notation but hex takes an optional fourth component, the alpha from
+// 0.0 to 1.0, as in `R,G,B,A`.
+
+/// A named color component and its allowed range. Hues wrap around into
+/// their range instead of being rejected.
+struct Component {
+    name: &'static str,
+    min: f32,
+    max: f32,
+    wraps: bool,
+}
+
+const fn component(name: &'static str, min: f32, max: f32) -> Component {
+    Component {
+        name,
+        min,
+        max,
+        wraps: false,
+    }
+}
+
+const fn hue(name: &'static str) -> Component {
+    Component {
+        name,
+        min: 0.0,
+        max: 360.0,
+        wraps: true,
+    }
+}
+
+const RGB: [Component; 3] = [
+    component("Red", 0.0, 255.0),
+    component("Green", 0.0, 255.0),
+    component("Blue", 0.0, 255.0),
+];
+const DECIMAL: [Component; 3] = [
+    component("Red", 0.0, 1.0),
+    component("Green", 0.0, 1.0),
+    component("Blue", 0.0, 1.0),
+];
+const HSV: [Component; 3] = [
+    hue("Hue"),
+    component("Saturation", 0.0, 100.0),
+    component("Value", 0.0, 100.0),
+];
+const LCH: [Component; 3] = [
+    component("Lightness", 0.0, 100.0),
+    component("Chroma", 0.0, 150.0),
+    hue("Hue"),
+];
+const ALPHA: Component = component("Alpha", 0.0, 1.0);
+
+/// Describes what was wrong with a color.
+#[derive(Debug)]
+pub enum ColorErrorKind {
+    /// The color had the wrong number of components.
+    Count { expected: usize, found: usize },
+    /// A component couldn't be parsed or was out of range.
+    Component {
+        name: &'static str,
+        value: String,
+        min: f32,
+        max: f32,
+    },
+    /// A hex color had the wrong length or non-hex digits.
+    Hex,
+    /// The stop position after `@` couldn't be parsed or was out of range.
+    Position { value: String },
+}
+
+/// An error for a color which couldn't be parsed, with the flag it was given
+/// to and its 1-based index in that flag's list.
+#[derive(Debug)]
+pub struct ColorError {
+    pub flag: &'static str,
+    pub index: usize,
+    pub color: String,
+    pub kind: ColorErrorKind,
+}
+
+impl fmt::Display for ColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Could not parse color {} `{}` of {}: ",
+            self.index, self.color, self.flag
+        )?;
+        match self.kind {
+            ColorErrorKind::Count { expected, found } => write!(
+                f,
+                "expected {} components and an optional alpha delimited by `,`, found {}",
+                expected, found
+            ),
+            ColorErrorKind::Component {
+                name,
+                ref value,
+                min,
+                max,
+            } => write!(
+                f,
+                "{} `{}` should be a number from {} to {}",
+                name, value, min, max
+            ),
+            ColorErrorKind::Hex => write!(f, "value should be #RGB, #RGBA, #RRGGBB or #RRGGBBAA"),
+            ColorErrorKind::Position { ref value } => {
+                write!(f, "position `{}` should be a number from 0 to 1", value)
+            }
+        }
+    }
+}
+
+impl Error for ColorError {}
+
+/// A color and its optional position in the gradient, written as `color@0.5`.
+#[derive(Clone, Copy, Debug)]
+pub struct ColorStop {
+    pub color: Lcha<D65>,
+    pub position: Option<f32>,
+}
+
+/// Parses every color given to `flag` in `notation`.
+pub fn parse_colors<'a, I>(
+    flag: &'static str,
+    notation: Notation,
+    colors: I,
+) -> Result<Vec<ColorStop>, CliError>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    colors
+        .into_iter()
+        .enumerate()
+        .map(|(i, color)| parse_color_at(flag, i + 1, notation, color))
+        .collect()
+}
+
+/// Parses a single color given to `flag`, reporting its 1-based `index` on
+/// errors.
+pub(crate) fn parse_color_at(
+    flag: &'static str,
+    index: usize,
+    notation: Notation,
+    color: &str,
+) -> Result<ColorStop, CliError> {
+    parse_stop(notation, color).map_err(|kind| {
+        CliError::from(ColorError {
+            flag,
+            index,
+            color: color.to_string(),
+            kind,
+        })
+    })
+}
+
+/// Parses a color written in `notation` with an optional `@position`.
+fn parse_stop(notation: Notation, stop: &str) -> Result<ColorStop, ColorErrorKind> {
+    let mut parts = stop.splitn(2, '@');
+    let color = parse_color(notation, parts.next().unwrap_or(""))?;
+    let position = match parts.next().map(str::trim) {
+        Some(value) => match value.parse::<f32>() {
+            Ok(p) if (0.0..=1.0).contains(&p) => Some(p),
+            _ => {
+                return Err(ColorErrorKind::Position {
+                    value: value.to_string(),
+                })
+            }
+        },
+        None => None,
+    };
+
+    Ok(ColorStop { color, position })
+}
+
+/// Parses a single color written in `notation`.
+fn parse_color(notation: Notation, color: &str) -> Result<Lcha<D65>, ColorErrorKind> {
+    match notation {
+        Notation::Rgb => {
+            let [r, g, b, a] = parse_components(color, &RGB)?;
+            let rgb =
+                Srgb::new(r.round() as u8, g.round() as u8, b.round() as u8).into_format::<f32>();
+            Ok(Lcha::from(
+                Srgba::new(rgb.red, rgb.green, rgb.blue, a).into_linear(),
+            ))
+        }
+        Notation::Decimal => {
+            let [r, g, b, a] = parse_components(color, &DECIMAL)?;
+            Ok(Lcha::from(Srgba::new(r, g, b, a).into_linear()))
+        }
+        Notation::Hex => {
+            let rgba = parse_hex(color).ok_or(ColorErrorKind::Hex)?;
+            Ok(Lcha::from(rgba.into_format::<f32, f32>().into_linear()))
+        }
+        Notation::Hsv => {
+            let [h, s, v, a] = parse_components(color, &HSV)?;
+            Ok(Lcha::from(Hsva::new(h, s / 100.0, v / 100.0, a)))
+        }
+        Notation::Lch => {
+            let [l, chroma, hue, a] = parse_components(color, &LCH)?;
+            Ok(Lcha::new(l, chroma, hue, a))
+        }
+    }
+}
+
+/// Splits `color` on `,` and checks each value against its component. The
+/// alpha is 1.0 unless given as a fourth value.
+fn parse_components(color: &str, components: &[Component; 3]) -> Result<[f32; 4], ColorErrorKind> {
+    let values = color.split(',').map(str::trim).collect::<Vec<_>>();
+    if values.len() != components.len() && values.len() != components.len() + 1 {
+        return Err(ColorErrorKind::Count {
+            expected: components.len(),
+            found: values.len(),
+        });
+    }
+
+    let mut result = [0.0, 0.0, 0.0, 1.0];
+    let components = components.iter().chain(Some(&ALPHA));
+    for (out, (value, c)) in result.iter_mut().zip(values.iter().zip(components)) {
+        *out = match value.parse::<f32>() {
+            Ok(v) if c.wraps && v.is_finite() => v.rem_euclid(c.max),
+            Ok(v) if v >= c.min && v <= c.max => v,
+            _ => {
+                return Err(ColorErrorKind::Component {
+                    name: c.name,
+                    value: value.to_string(),
+                    min: c.min,
+                    max: c.max,
+                })
+            }
+        };
+    }
+
+    Ok(result)
+}
+
+/// Parses a hex color in `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA` format.
+/// The leading `#` is optional.
+fn parse_hex(hex: &str) -> Option<Srgba<u8>> {
+    let digits = hex.trim_start_matches('#');
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let short = |i: usize| u8::from_str_radix(&digits[i..=i], 16).ok().map(|v| v * 17);
+    let long = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+
+    match digits.len() {
+        3 => Some(Srgba::new(short(0)?, short(1)?, short(2)?, 255)),
+        4 => Some(Srgba::new(short(0)?, short(1)?, short(2)?, short(3)?)),
+        6 => Some(Srgba::new(long(0)?, long(2)?, long(4)?, 255)),
+        8 => Some(Srgba::new(long(0)?, long(2)?, long(4)?, long(6)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex_lengths() {
+        assert_eq!(parse_hex("#e85348"), Some(Srgba::new(232, 83, 72, 255)));
+        assert_eq!(parse_hex("E85348"), Some(Srgba::new(232, 83, 72, 255)));
+        assert_eq!(parse_hex("#e8534880"), Some(Srgba::new(232, 83, 72, 128)));
+        assert_eq!(parse_hex("#f80"), Some(Srgba::new(255, 136, 0, 255)));
+        assert_eq!(parse_hex("#f808"), Some(Srgba::new(255, 136, 0, 136)));
+    }
+
+    #[test]
+    fn parse_hex_rejects_invalid() {
+        for hex in &["", "#", "#e8534", "#e853481", "#e8534g", "+1e853", "#é85"] {
+            assert_eq!(parse_hex(hex), None, "{}", hex);
+        }
+    }
+}
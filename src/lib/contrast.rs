@@ -0,0 +1,373 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use palette::{Pixel, Srgb, Srgba};
+
+use crate::{generate_filename, save_image_alpha, write_report, Config, OutputFormat};
+
+/// Color of the mark on passing cells of the matrix image.
+const PASS_MARK: [u8; 4] = [0, 170, 70, 255];
+
+/// Color of the mark on failing cells of the matrix image.
+const FAIL_MARK: [u8; 4] = [210, 30, 30, 255];
+
+/// How the contrast report is written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContrastFormat {
+    Table,
+    Json,
+    Image,
+}
+
+impl fmt::Display for ContrastFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ContrastFormat::Table => write!(f, "table"),
+            ContrastFormat::Json => write!(f, "json"),
+            ContrastFormat::Image => write!(f, "image"),
+        }
+    }
+}
+
+impl FromStr for ContrastFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "table" => Ok(ContrastFormat::Table),
+            "json" => Ok(ContrastFormat::Json),
+            "image" => Ok(ContrastFormat::Image),
+            _ => Err(format!("Unsupported contrast report format `{}`", s)),
+        }
+    }
+}
+
+/// The level a pair of colors has to reach to pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContrastTarget {
+    /// WCAG 2.1 AA for normal text, a ratio of at least 4.5:1.
+    Aa,
+    /// WCAG 2.1 AAA for normal text, a ratio of at least 7:1.
+    Aaa,
+    /// An APCA lightness contrast of at least this magnitude.
+    Lc(f32),
+}
+
+impl ContrastTarget {
+    fn passes(self, ratio: f32, lc: f32) -> bool {
+        match self {
+            ContrastTarget::Aa => ratio >= 4.5,
+            ContrastTarget::Aaa => ratio >= 7.0,
+            ContrastTarget::Lc(threshold) => lc.abs() >= threshold,
+        }
+    }
+}
+
+impl fmt::Display for ContrastTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ContrastTarget::Aa => write!(f, "AA"),
+            ContrastTarget::Aaa => write!(f, "AAA"),
+            ContrastTarget::Lc(threshold) => write!(f, "Lc {}", threshold),
+        }
+    }
+}
+
+impl FromStr for ContrastTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "aa" => Ok(ContrastTarget::Aa),
+            "aaa" => Ok(ContrastTarget::Aaa),
+            lc => match lc.trim_start_matches("lc").trim().parse::<f32>() {
+                Ok(threshold) if threshold >= 0.0 => Ok(ContrastTarget::Lc(threshold)),
+                _ => Err(format!(
+                    "Unsupported contrast target `{}`, expected AA, AAA or an APCA Lc value",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// The WCAG 2.1 relative luminance of `color`.
+fn relative_luminance(color: Srgb) -> f32 {
+    let lin = color.into_linear();
+    0.2126 * lin.red + 0.7152 * lin.green + 0.0722 * lin.blue
+}
+
+/// The WCAG 2.1 contrast ratio of two colors, from 1.0 to 21.0.
+fn wcag_contrast(a: Srgb, b: Srgb) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// The APCA 0.0.98G lightness contrast of `text` on `background`. Positive
+/// for dark text on a light background, negative for light text on a dark
+/// background.
+fn apca_contrast(text: Srgb, background: Srgb) -> f32 {
+    // APCA uses a simple power curve instead of the sRGB transfer function.
+    let screen_luminance = |c: Srgb| {
+        let y = 0.212_672_9 * c.red.powf(2.4)
+            + 0.715_152_2 * c.green.powf(2.4)
+            + 0.072_175 * c.blue.powf(2.4);
+        // Soft clamp of near black.
+        if y < 0.022 {
+            y + (0.022 - y).powf(1.414)
+        } else {
+            y
+        }
+    };
+    let (y_text, y_back) = (screen_luminance(text), screen_luminance(background));
+    if (y_back - y_text).abs() < 0.0005 {
+        return 0.0;
+    }
+
+    let lc = if y_back > y_text {
+        let s = (y_back.powf(0.56) - y_text.powf(0.57)) * 1.14;
+        if s < 0.1 {
+            0.0
+        } else {
+            s - 0.027
+        }
+    } else {
+        let s = (y_back.powf(0.65) - y_text.powf(0.62)) * 1.14;
+        if s > -0.1 {
+            0.0
+        } else {
+            s + 0.027
+        }
+    };
+    lc * 100.0
+}
+
+/// Contrast of every pair of colors, with text colors in rows and background
+/// colors in columns.
+struct ContrastMatrix {
+    colors: Vec<Srgb>,
+    wcag: Vec<Vec<f32>>,
+    apca: Vec<Vec<f32>>,
+    pass: Vec<Vec<bool>>,
+}
+
+impl ContrastMatrix {
+    fn new(colors: &[Srgba], target: ContrastTarget) -> ContrastMatrix {
+        let colors: Vec<Srgb> = colors.iter().map(|c| c.color).collect();
+        let table = |f: &dyn Fn(Srgb, Srgb) -> f32| -> Vec<Vec<f32>> {
+            colors
+                .iter()
+                .map(|&text| colors.iter().map(|&back| f(text, back)).collect())
+                .collect()
+        };
+        let wcag = table(&wcag_contrast);
+        let apca = table(&apca_contrast);
+        let pass = wcag
+            .iter()
+            .zip(&apca)
+            .map(|(w, a)| {
+                w.iter()
+                    .zip(a)
+                    .map(|(&w, &a)| target.passes(w, a))
+                    .collect()
+            })
+            .collect();
+        ContrastMatrix {
+            colors,
+            wcag,
+            apca,
+            pass,
+        }
+    }
+
+    fn hex(&self) -> Vec<String> {
+        self.colors
+            .iter()
+            .map(|c| format!("{:x}", c.into_format::<u8>()))
+            .collect()
+    }
+
+    fn table(&self, target: ContrastTarget) -> Result<String, fmt::Error> {
+        let hex = self.hex();
+        let mut out = String::new();
+        // Only the measure of the target is marked.
+        let wcag_marked = !matches!(target, ContrastTarget::Lc(_));
+        let sections = [
+            ("WCAG 2.1 contrast ratio", &self.wcag, wcag_marked),
+            ("APCA Lc", &self.apca, !wcag_marked),
+        ];
+        for (i, &(title, values, marked)) in sections.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            writeln!(out, "{}, text in rows on background in columns", title)?;
+            write!(out, "{:8}", "")?;
+            for h in &hex {
+                write!(out, " {:>12}", h)?;
+            }
+            out.push('\n');
+            for (r, row) in values.iter().enumerate() {
+                let mut line = format!("{:8}", hex[r]);
+                for (c, value) in row.iter().enumerate() {
+                    let mark = match (marked, self.pass[r][c]) {
+                        (false, _) => "",
+                        (true, true) => "pass",
+                        (true, false) => "fail",
+                    };
+                    write!(line, " {:>7.2} {:4}", value, mark)?;
+                }
+                writeln!(out, "{}", line.trim_end())?;
+            }
+        }
+        writeln!(out, "\nPass or fail against {}", target)?;
+        Ok(out)
+    }
+
+    fn json(&self, target: ContrastTarget) -> Result<String, fmt::Error> {
+        let list = |values: Vec<String>| format!("[{}]", values.join(", "));
+        let rows = |rows: Vec<String>| format!("[\n    {}\n  ]", rows.join(",\n    "));
+        let numbers = |values: &Vec<Vec<f32>>| {
+            rows(
+                values
+                    .iter()
+                    .map(|row| list(row.iter().map(|v| format!("{:.2}", v)).collect()))
+                    .collect(),
+            )
+        };
+
+        let mut out = String::from("{\n");
+        writeln!(out, "  \"target\": \"{}\",", target)?;
+        writeln!(
+            out,
+            "  \"colors\": {},",
+            list(self.hex().iter().map(|h| format!("\"{}\"", h)).collect())
+        )?;
+        writeln!(out, "  \"wcag\": {},", numbers(&self.wcag))?;
+        writeln!(out, "  \"apca\": {},", numbers(&self.apca))?;
+        writeln!(
+            out,
+            "  \"pass\": {}",
+            rows(
+                self.pass
+                    .iter()
+                    .map(|row| list(row.iter().map(bool::to_string).collect()))
+                    .collect()
+            )
+        )?;
+        out.push_str("}\n");
+        Ok(out)
+    }
+
+    /// Draws the matrix with the colors along the top and left. Each cell has
+    /// the background color of its column, a bar of the text color of its
+    /// row, and a pass or fail mark in its corner.
+    fn image(&self, (sw, sh): (u32, u32)) -> image::RgbaImage {
+        let n = self.colors.len() as u32;
+        let raw: Vec<[u8; 4]> = self
+            .colors
+            .iter()
+            .map(|c| Srgba::from(*c).into_format().into_raw())
+            .collect();
+        let mark = (sw.min(sh) / 5).max(1);
+
+        let mut imgbuf: image::RgbaImage = image::ImageBuffer::new(sw * (n + 1), sh * (n + 1));
+        for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
+            let (col, row) = (x / sw, y / sh);
+            let (cx, cy) = (x % sw, y % sh);
+            let pix = match (row, col) {
+                (0, 0) => [0, 0, 0, 0],
+                (0, c) => raw[c as usize - 1],
+                (r, 0) => raw[r as usize - 1],
+                (r, c) => {
+                    let (r, c) = (r as usize - 1, c as usize - 1);
+                    if cx >= sw - mark && cy < mark {
+                        if self.pass[r][c] {
+                            PASS_MARK
+                        } else {
+                            FAIL_MARK
+                        }
+                    } else if cx >= sw / 4
+                        && cx < sw - sw / 4
+                        && cy >= sh * 2 / 5
+                        && cy < sh - sh * 2 / 5
+                    {
+                        raw[r]
+                    } else {
+                        raw[c]
+                    }
+                }
+            };
+            *pixel = image::Rgba(pix);
+        }
+        imgbuf
+    }
+}
+
+/// Writes the contrast of every pair of `colors` in the format set with
+/// `--contrast`, if any. Tables and JSON go to `--contrast-file` or stdout,
+/// images to `--contrast-file` or a timestamped PNG. Alpha is ignored.
+pub(crate) fn report_contrast(config: &Config, colors: &[Srgba]) -> Result<(), Box<dyn Error>> {
+    let format = match config.contrast {
+        Some(format) => format,
+        None => return Ok(()),
+    };
+    let matrix = ContrastMatrix::new(colors, config.contrast_target);
+
+    let text = match format {
+        ContrastFormat::Table => matrix.table(config.contrast_target)?,
+        ContrastFormat::Json => matrix.json(config.contrast_target)?,
+        ContrastFormat::Image => {
+            let title = match &config.contrast_file {
+                Some(path) => path.clone(),
+                None => PathBuf::from(generate_filename()? + "-contrast.png"),
+            };
+            let image_format = OutputFormat::from_path(&title)
+                .filter(|&f| f != OutputFormat::Svg)
+                .ok_or_else(|| {
+                    format!(
+                        "Could not determine the image format of {}",
+                        title.display()
+                    )
+                })?;
+            return save_image_alpha(&matrix.image(config.swatch_size), &title, image_format);
+        }
+    };
+    write_report(&text, config.contrast_file.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(value: f32) -> Srgb {
+        Srgb::new(value, value, value)
+    }
+
+    #[test]
+    fn wcag_black_on_white() {
+        let (black, white) = (gray(0.0), gray(1.0));
+        assert!((wcag_contrast(black, white) - 21.0).abs() < 1e-4);
+        assert!((wcag_contrast(white, black) - 21.0).abs() < 1e-4);
+        assert_eq!(wcag_contrast(white, white), 1.0);
+    }
+
+    #[test]
+    fn apca_black_and_white() {
+        let (black, white) = (gray(0.0), gray(1.0));
+        // Reference values of the APCA 0.0.98G calculator.
+        assert!((apca_contrast(black, white) - 106.04).abs() < 0.01);
+        assert!((apca_contrast(white, black) + 107.88).abs() < 0.01);
+        assert_eq!(apca_contrast(white, white), 0.0);
+    }
+
+    #[test]
+    fn apca_mid_gray() {
+        // #888 text on white and white text on #888.
+        let (mid, white) = (gray(0x88 as f32 / 255.0), gray(1.0));
+        assert!((apca_contrast(mid, white) - 63.06).abs() < 0.01);
+        assert!((apca_contrast(white, mid) + 68.54).abs() < 0.01);
+    }
+}
@@ -0,0 +1,416 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use palette::{LinSrgba, Pixel, Srgba};
+
+use super::gamut::{clip, Gamut};
+use crate::{output_path, print_colors, save_image_alpha, ColorGradient, Config};
+
+/// Machado et al. (2009) matrices for protanomaly, severity 0.0 to 1.0 in
+/// steps of 0.1.
+const PROTAN: [[[f32; 3]; 3]; 11] = [
+    [
+        [1.000000, 0.000000, 0.000000],
+        [0.000000, 1.000000, 0.000000],
+        [0.000000, 0.000000, 1.000000],
+    ],
+    [
+        [0.856167, 0.182038, -0.038205],
+        [0.029342, 0.955115, 0.015544],
+        [-0.002880, -0.001563, 1.004443],
+    ],
+    [
+        [0.734766, 0.334872, -0.069637],
+        [0.051840, 0.919198, 0.028963],
+        [-0.004928, -0.004209, 1.009137],
+    ],
+    [
+        [0.630323, 0.465641, -0.095964],
+        [0.069181, 0.890046, 0.040773],
+        [-0.006308, -0.007724, 1.014032],
+    ],
+    [
+        [0.539009, 0.579343, -0.118352],
+        [0.082546, 0.866121, 0.051332],
+        [-0.007136, -0.011959, 1.019095],
+    ],
+    [
+        [0.458064, 0.679578, -0.137642],
+        [0.092785, 0.846313, 0.060902],
+        [-0.007494, -0.016807, 1.024301],
+    ],
+    [
+        [0.385450, 0.769005, -0.154455],
+        [0.100526, 0.829802, 0.069673],
+        [-0.007442, -0.022190, 1.029632],
+    ],
+    [
+        [0.319627, 0.849633, -0.169261],
+        [0.106241, 0.815969, 0.077790],
+        [-0.007025, -0.028051, 1.035076],
+    ],
+    [
+        [0.259411, 0.923008, -0.182420],
+        [0.110296, 0.804340, 0.085364],
+        [-0.006276, -0.034346, 1.040622],
+    ],
+    [
+        [0.203876, 0.990338, -0.194214],
+        [0.112975, 0.794542, 0.092483],
+        [-0.005222, -0.041043, 1.046265],
+    ],
+    [
+        [0.152286, 1.052583, -0.204868],
+        [0.114503, 0.786281, 0.099216],
+        [-0.003882, -0.048116, 1.051998],
+    ],
+];
+
+/// Machado et al. (2009) matrices for deuteranomaly.
+const DEUTAN: [[[f32; 3]; 3]; 11] = [
+    [
+        [1.000000, 0.000000, 0.000000],
+        [0.000000, 1.000000, 0.000000],
+        [0.000000, 0.000000, 1.000000],
+    ],
+    [
+        [0.866435, 0.177704, -0.044139],
+        [0.049567, 0.939063, 0.011370],
+        [-0.003453, 0.007233, 0.996220],
+    ],
+    [
+        [0.760729, 0.319078, -0.079807],
+        [0.090568, 0.889315, 0.020117],
+        [-0.006027, 0.013325, 0.992702],
+    ],
+    [
+        [0.675425, 0.433850, -0.109275],
+        [0.125303, 0.847755, 0.026942],
+        [-0.007950, 0.018572, 0.989378],
+    ],
+    [
+        [0.605511, 0.528560, -0.134071],
+        [0.155318, 0.812366, 0.032316],
+        [-0.009376, 0.023176, 0.986200],
+    ],
+    [
+        [0.547494, 0.607765, -0.155259],
+        [0.181692, 0.781742, 0.036566],
+        [-0.010410, 0.027275, 0.983136],
+    ],
+    [
+        [0.498864, 0.674741, -0.173604],
+        [0.205199, 0.754872, 0.039929],
+        [-0.011131, 0.030969, 0.980162],
+    ],
+    [
+        [0.457771, 0.731899, -0.189670],
+        [0.226409, 0.731012, 0.042579],
+        [-0.011595, 0.034333, 0.977261],
+    ],
+    [
+        [0.422823, 0.781057, -0.203881],
+        [0.245752, 0.709602, 0.044646],
+        [-0.011843, 0.037423, 0.974421],
+    ],
+    [
+        [0.392952, 0.823610, -0.216562],
+        [0.263559, 0.690210, 0.046232],
+        [-0.011910, 0.040281, 0.971630],
+    ],
+    [
+        [0.367322, 0.860646, -0.227968],
+        [0.280085, 0.672501, 0.047413],
+        [-0.011820, 0.042940, 0.968881],
+    ],
+];
+
+/// Machado et al. (2009) matrices for tritanomaly.
+const TRITAN: [[[f32; 3]; 3]; 11] = [
+    [
+        [1.000000, 0.000000, 0.000000],
+        [0.000000, 1.000000, 0.000000],
+        [0.000000, 0.000000, 1.000000],
+    ],
+    [
+        [0.926670, 0.092514, -0.019184],
+        [0.021191, 0.964503, 0.014306],
+        [0.008437, 0.054813, 0.936750],
+    ],
+    [
+        [0.895720, 0.133330, -0.029050],
+        [0.029997, 0.945400, 0.024603],
+        [0.013027, 0.104707, 0.882266],
+    ],
+    [
+        [0.905871, 0.127791, -0.033662],
+        [0.026856, 0.941251, 0.031893],
+        [0.013410, 0.148296, 0.838294],
+    ],
+    [
+        [0.948035, 0.089490, -0.037526],
+        [0.014364, 0.946792, 0.038844],
+        [0.010853, 0.193991, 0.795156],
+    ],
+    [
+        [1.017277, 0.027029, -0.044306],
+        [-0.006113, 0.958479, 0.047634],
+        [0.006379, 0.248708, 0.744913],
+    ],
+    [
+        [1.104996, -0.046633, -0.058363],
+        [-0.032137, 0.971635, 0.060503],
+        [0.001336, 0.317922, 0.680742],
+    ],
+    [
+        [1.193214, -0.109812, -0.083402],
+        [-0.058496, 0.979410, 0.079086],
+        [-0.002346, 0.403492, 0.598854],
+    ],
+    [
+        [1.257728, -0.139648, -0.118081],
+        [-0.078003, 0.975409, 0.102594],
+        [-0.003316, 0.501214, 0.502102],
+    ],
+    [
+        [1.278864, -0.125333, -0.153531],
+        [-0.084748, 0.957674, 0.127074],
+        [-0.000989, 0.601151, 0.399838],
+    ],
+    [
+        [1.255528, -0.076749, -0.178779],
+        [-0.078411, 0.930809, 0.147602],
+        [0.004733, 0.691367, 0.303900],
+    ],
+];
+
+/// A color vision deficiency.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Deficiency {
+    /// Missing or anomalous long wavelength cones.
+    Protan,
+    /// Missing or anomalous medium wavelength cones.
+    Deutan,
+    /// Missing or anomalous short wavelength cones.
+    Tritan,
+    /// No color vision, only luminance is seen.
+    Achromat,
+}
+
+impl Deficiency {
+    /// Every deficiency, in the order of the comparison sheet.
+    pub const ALL: [Deficiency; 4] = [
+        Deficiency::Protan,
+        Deficiency::Deutan,
+        Deficiency::Tritan,
+        Deficiency::Achromat,
+    ];
+}
+
+impl fmt::Display for Deficiency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Deficiency::Protan => "protan",
+            Deficiency::Deutan => "deutan",
+            Deficiency::Tritan => "tritan",
+            Deficiency::Achromat => "achromat",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Deficiency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "protan" | "protanopia" | "protanomaly" => Ok(Deficiency::Protan),
+            "deutan" | "deuteranopia" | "deuteranomaly" => Ok(Deficiency::Deutan),
+            "tritan" | "tritanopia" | "tritanomaly" => Ok(Deficiency::Tritan),
+            "achromat" | "achromatopsia" | "achromatomaly" => Ok(Deficiency::Achromat),
+            _ => Err(format!("Unsupported color vision deficiency `{}`", s)),
+        }
+    }
+}
+
+/// Simulates how colors look with a color vision deficiency, following
+/// Machado et al. (2009). A `severity` of 1.0 is the full deficiency, such as
+/// protanopia, and lower values are the anomalous forms, such as
+/// protanomaly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Simulation {
+    pub deficiency: Deficiency,
+    pub severity: f32,
+}
+
+impl Simulation {
+    /// Applies the simulation to a linear color, keeping its alpha. The
+    /// result can be outside of [0.0, 1.0].
+    pub fn apply(self, color: LinSrgba) -> LinSrgba {
+        let rgb = [color.red, color.green, color.blue];
+        let severity = self.severity.clamp(0.0, 1.0);
+        let matrix = match self.deficiency {
+            Deficiency::Protan => machado(&PROTAN, severity),
+            Deficiency::Deutan => machado(&DEUTAN, severity),
+            Deficiency::Tritan => machado(&TRITAN, severity),
+            Deficiency::Achromat => {
+                // Mix towards the luminance of the color.
+                let y = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+                let mix = |c: f32| c + severity * (y - c);
+                return LinSrgba::new(mix(rgb[0]), mix(rgb[1]), mix(rgb[2]), color.alpha);
+            }
+        };
+        let row = |r: [f32; 3]| r[0] * rgb[0] + r[1] * rgb[1] + r[2] * rgb[2];
+        LinSrgba::new(row(matrix[0]), row(matrix[1]), row(matrix[2]), color.alpha)
+    }
+}
+
+impl fmt::Display for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.deficiency, self.severity)
+    }
+}
+
+/// Simulates `simulation`, if any, on a color that is about to be drawn,
+/// clipping the result into gamut. Only rendered pixels are simulated, printed
+/// colors, saved palettes and reports keep the original colors.
+pub(crate) fn simulate(simulation: Option<Simulation>, color: Srgba) -> Srgba {
+    match simulation {
+        Some(simulation) => Srgba::from_linear(clip(simulation.apply(color.into_linear()))),
+        None => color,
+    }
+}
+
+/// Interpolates the matrix for `severity` between the two nearest of
+/// `table`.
+fn machado(table: &[[[f32; 3]; 3]; 11], severity: f32) -> [[f32; 3]; 3] {
+    let scaled = severity * 10.0;
+    let low = (scaled.floor() as usize).min(9);
+    let factor = scaled - low as f32;
+    let mut matrix = [[0.0; 3]; 3];
+    for (r, row) in matrix.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            let (a, b) = (table[low][r][c], table[low + 1][r][c]);
+            *value = a + factor * (b - a);
+        }
+    }
+    matrix
+}
+
+/// Creates a sheet comparing the stepped gradient as seen with normal color
+/// vision in the first row to each simulated deficiency in the rows below.
+/// Only the deficiency of `--simulate` is shown if one was given, otherwise
+/// every deficiency at `--severity`. With `--print`, each row's colors are
+/// printed on their own line.
+pub fn simulation_sheet(config: Config) -> Result<(), Box<dyn Error>> {
+    let grad = ColorGradient::new(&config);
+    let steps = grad.take_spaced(config.steps, config.spacing);
+    config.spacing.report(&steps);
+    let simulations: Vec<Option<Simulation>> = match config.simulate {
+        Some(simulation) => vec![None, Some(simulation)],
+        None => std::iter::once(None)
+            .chain(Deficiency::ALL.iter().map(|&deficiency| {
+                Some(Simulation {
+                    deficiency,
+                    severity: config.severity,
+                })
+            }))
+            .collect(),
+    };
+
+    let gamut = Gamut::new(&config);
+    let colors: Vec<Srgba> = steps.iter().map(|&c| gamut.to_srgb(c)).collect();
+    gamut.report(config.gamut_report, "steps");
+    let rows: Vec<Vec<Srgba>> = simulations
+        .iter()
+        .map(|&simulation| colors.iter().map(|&c| simulate(simulation, c)).collect())
+        .collect();
+
+    if config.print_grad {
+        rows.iter().for_each(|row| print_colors(row));
+    }
+    if config.no_file {
+        return Ok(());
+    }
+
+    let (sw, sh) = config.swatch_size;
+    let mut imgbuf: image::RgbaImage =
+        image::ImageBuffer::new(sw * steps.len() as u32, sh * rows.len() as u32);
+
+    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
+        let color = rows[(y / sh) as usize][(x / sw) as usize];
+        *pixel = image::Rgba(color.into_format().into_raw());
+    }
+
+    let title = output_path(config.output_file.as_deref(), config.format)?;
+
+    save_image_alpha(&imgbuf, &title, config.format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(result: LinSrgba, expected: [f32; 3]) {
+        let channels = [result.red, result.green, result.blue];
+        for (c, e) in channels.iter().zip(expected.iter()) {
+            assert!((c - e).abs() < 1e-5, "{:?} != {:?}", channels, expected);
+        }
+        assert_eq!(result.alpha, 1.0);
+    }
+
+    #[test]
+    fn full_severity_follows_machado() {
+        // Pure red and green give the first and second column of each
+        // matrix at severity 1.0 in Machado et al. (2009).
+        let red = LinSrgba::new(1.0, 0.0, 0.0, 1.0);
+        let green = LinSrgba::new(0.0, 1.0, 0.0, 1.0);
+        for &(deficiency, red_result, green_result) in &[
+            (
+                Deficiency::Protan,
+                [0.152286, 0.114503, -0.003882],
+                [1.052583, 0.786281, -0.048116],
+            ),
+            (
+                Deficiency::Deutan,
+                [0.367322, 0.280085, -0.011820],
+                [0.860646, 0.672501, 0.042940],
+            ),
+            (
+                Deficiency::Tritan,
+                [1.255528, -0.078411, 0.004733],
+                [-0.076749, 0.930809, 0.691367],
+            ),
+        ] {
+            let simulation = Simulation {
+                deficiency,
+                severity: 1.0,
+            };
+            assert_close(simulation.apply(red), red_result);
+            assert_close(simulation.apply(green), green_result);
+        }
+    }
+
+    #[test]
+    fn full_achromat_sees_luminance() {
+        let simulation = Simulation {
+            deficiency: Deficiency::Achromat,
+            severity: 1.0,
+        };
+        assert_close(
+            simulation.apply(LinSrgba::new(1.0, 0.0, 0.0, 1.0)),
+            [0.2126; 3],
+        );
+        assert_close(
+            simulation.apply(LinSrgba::new(0.0, 1.0, 0.0, 1.0)),
+            [0.7152; 3],
+        );
+    }
+
+    #[test]
+    fn no_simulation_keeps_colors() {
+        let color = Srgba::new(0.2, 0.4, 0.6, 0.8);
+        assert_eq!(simulate(None, color), color);
+    }
+}
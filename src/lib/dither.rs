@@ -0,0 +1,332 @@
+use std::fmt;
+use std::str::FromStr;
+
+use palette::{Pixel, Srgba};
+
+use super::rng::SplitMix64;
+
+/// Side of the square Bayer matrix.
+const BAYER_SIZE: usize = 8;
+
+/// Side of the square blue-noise mask.
+const BLUE_NOISE_SIZE: usize = 64;
+
+/// Spread of the Gaussian filter used to find clusters and voids when
+/// generating blue noise.
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+
+/// How colors are dithered before they're quantized to 8 bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DitherMethod {
+    /// Round each channel to the nearest value.
+    None,
+    /// Ordered dithering with an 8x8 Bayer matrix.
+    Bayer,
+    /// Ordered dithering with a 64x64 blue-noise mask made with the
+    /// void-and-cluster method.
+    BlueNoise,
+    /// Floyd–Steinberg error diffusion.
+    FloydSteinberg,
+}
+
+impl fmt::Display for DitherMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            DitherMethod::None => "none",
+            DitherMethod::Bayer => "bayer",
+            DitherMethod::BlueNoise => "blue-noise",
+            DitherMethod::FloydSteinberg => "floyd-steinberg",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for DitherMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(DitherMethod::None),
+            "bayer" => Ok(DitherMethod::Bayer),
+            "blue-noise" | "bluenoise" => Ok(DitherMethod::BlueNoise),
+            "floyd-steinberg" | "fs" => Ok(DitherMethod::FloydSteinberg),
+            _ => Err(format!("Unsupported dithering method `{}`", s)),
+        }
+    }
+}
+
+/// Thresholds in [0.0, 1.0) of a square Bayer matrix of side `size`, a power
+/// of two, built up recursively from the 2x2 matrix.
+fn bayer_matrix(size: usize) -> Vec<f32> {
+    let mut matrix = vec![0u32];
+    let mut n = 1;
+    while n < size {
+        let mut next = vec![0u32; 4 * n * n];
+        for y in 0..n {
+            for x in 0..n {
+                let v = 4 * matrix[y * n + x];
+                next[y * 2 * n + x] = v;
+                next[y * 2 * n + x + n] = v + 2;
+                next[(y + n) * 2 * n + x] = v + 3;
+                next[(y + n) * 2 * n + x + n] = v + 1;
+            }
+        }
+        matrix = next;
+        n *= 2;
+    }
+    let count = (size * size) as f32;
+    matrix.iter().map(|&v| (v as f32 + 0.5) / count).collect()
+}
+
+/// Thresholds in [0.0, 1.0) of a square blue-noise mask of side `size`,
+/// ranked with the void-and-cluster method of Ulichney (1993). The initial
+/// pattern is seeded by `seed`.
+fn blue_noise_mask(size: usize, seed: u64) -> Vec<f32> {
+    let count = size * size;
+
+    // Gaussian weights for every wrapped offset, so the mask tiles.
+    let mut kernel = vec![0.0f32; count];
+    for dy in 0..size {
+        for dx in 0..size {
+            let wrap = |d: usize| d.min(size - d) as f32;
+            let r_squared = wrap(dx).powi(2) + wrap(dy).powi(2);
+            kernel[dy * size + dx] = (-r_squared / (2.0 * BLUE_NOISE_SIGMA.powi(2))).exp();
+        }
+    }
+
+    // `energy[i]` is the filtered density of the set pixels around `i`.
+    let update = |energy: &mut [f32], i: usize, sign: f32| {
+        let (x0, y0) = (i % size, i / size);
+        for y in 0..size {
+            let dy = (y + size - y0) % size;
+            for x in 0..size {
+                let dx = (x + size - x0) % size;
+                energy[y * size + x] += sign * kernel[dy * size + dx];
+            }
+        }
+    };
+    // The set pixel with the highest energy, or the unset pixel with the
+    // lowest.
+    let tightest_cluster = |pattern: &[bool], energy: &[f32]| {
+        (0..count)
+            .filter(|&i| pattern[i])
+            .max_by(|&a, &b| energy[a].partial_cmp(&energy[b]).unwrap())
+            .unwrap()
+    };
+    let largest_void = |pattern: &[bool], energy: &[f32]| {
+        (0..count)
+            .filter(|&i| !pattern[i])
+            .min_by(|&a, &b| energy[a].partial_cmp(&energy[b]).unwrap())
+            .unwrap()
+    };
+
+    // A random initial pattern with a tenth of the pixels set, relaxed by
+    // moving its tightest cluster into its largest void until stable.
+    let mut rng = SplitMix64(seed);
+    let mut pattern = vec![false; count];
+    let mut energy = vec![0.0f32; count];
+    let initial = (count / 10).max(1);
+    let mut set = 0;
+    while set < initial {
+        let i = (rng.next_u64() % count as u64) as usize;
+        if !pattern[i] {
+            pattern[i] = true;
+            update(&mut energy, i, 1.0);
+            set += 1;
+        }
+    }
+    for _ in 0..count {
+        let cluster = tightest_cluster(&pattern, &energy);
+        pattern[cluster] = false;
+        update(&mut energy, cluster, -1.0);
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0usize; count];
+
+    // Rank the initial pattern by removing its tightest clusters.
+    let (mut removing, mut removing_energy) = (pattern.clone(), energy.clone());
+    for rank in (0..initial).rev() {
+        let cluster = tightest_cluster(&removing, &removing_energy);
+        removing[cluster] = false;
+        update(&mut removing_energy, cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    // Rank the remaining pixels by filling the largest voids. Once more than
+    // half are set, the largest void of the set pixels is also the tightest
+    // cluster of the unset ones, so the same step finishes the mask.
+    for rank in initial..count {
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .iter()
+        .map(|&r| (r as f32 + 0.5) / count as f32)
+        .collect()
+}
+
+/// Quantizes colors to 8 bits per channel with a dithering method. Pixels
+/// have to be passed in row-major order, like `enumerate_pixels_mut`, for
+/// error diffusion.
+pub(crate) struct Quantizer {
+    method: DitherMethod,
+    /// Threshold map and its side for ordered dithering.
+    thresholds: Vec<f32>,
+    size: usize,
+    /// Error diffused into the current and the next row, with a column of
+    /// padding on each side.
+    errors: [Vec<[f32; 3]>; 2],
+    row: u32,
+}
+
+impl Quantizer {
+    pub(crate) fn new(method: DitherMethod, width: u32, seed: u64) -> Quantizer {
+        let (thresholds, size) = match method {
+            DitherMethod::Bayer => (bayer_matrix(BAYER_SIZE), BAYER_SIZE),
+            DitherMethod::BlueNoise => (blue_noise_mask(BLUE_NOISE_SIZE, seed), BLUE_NOISE_SIZE),
+            DitherMethod::None | DitherMethod::FloydSteinberg => (Vec::new(), 0),
+        };
+        let padded = match method {
+            DitherMethod::FloydSteinberg => width as usize + 2,
+            _ => 0,
+        };
+        Quantizer {
+            method,
+            thresholds,
+            size,
+            errors: [vec![[0.0; 3]; padded], vec![[0.0; 3]; padded]],
+            row: 0,
+        }
+    }
+
+    /// Quantizes `color` at pixel (`x`, `y`). Alpha is rounded without
+    /// dithering.
+    pub(crate) fn quantize(&mut self, x: u32, y: u32, color: Srgba) -> [u8; 4] {
+        let alpha = (color.alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let channels = [color.red, color.green, color.blue];
+        let round = |v: f32| v.round().clamp(0.0, 255.0);
+
+        let rgb = match self.method {
+            DitherMethod::None => return color.into_format().into_raw(),
+            DitherMethod::Bayer | DitherMethod::BlueNoise => {
+                let index = (y as usize % self.size) * self.size + x as usize % self.size;
+                let offset = self.thresholds[index] - 0.5;
+                [
+                    round(channels[0] * 255.0 + offset),
+                    round(channels[1] * 255.0 + offset),
+                    round(channels[2] * 255.0 + offset),
+                ]
+            }
+            DitherMethod::FloydSteinberg => {
+                // Move on to the next row, which carries its own error, once
+                // for every row so none is carried over skipped rows.
+                while self.row < y {
+                    self.errors.swap(0, 1);
+                    self.errors[1].iter_mut().for_each(|e| *e = [0.0; 3]);
+                    self.row += 1;
+                }
+                let i = x as usize + 1;
+                if alpha == 0 {
+                    // Transparent pixels neither take nor spread error.
+                    self.errors[0][i] = [0.0; 3];
+                    return [
+                        round(channels[0] * 255.0) as u8,
+                        round(channels[1] * 255.0) as u8,
+                        round(channels[2] * 255.0) as u8,
+                        alpha,
+                    ];
+                }
+                let mut rgb = [0.0; 3];
+                for c in 0..3 {
+                    let value = channels[c] * 255.0 + self.errors[0][i][c];
+                    rgb[c] = round(value);
+                    let error = value - rgb[c];
+                    self.errors[0][i + 1][c] += error * 7.0 / 16.0;
+                    self.errors[1][i - 1][c] += error * 3.0 / 16.0;
+                    self.errors[1][i][c] += error * 5.0 / 16.0;
+                    self.errors[1][i + 1][c] += error / 16.0;
+                }
+                rgb
+            }
+        };
+        [rgb[0] as u8, rgb[1] as u8, rgb[2] as u8, alpha]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bayer_matrix_ranks() {
+        let ranks = |size: usize| -> Vec<usize> {
+            let count = (size * size) as f32;
+            bayer_matrix(size)
+                .iter()
+                .map(|&t| (t * count - 0.5).round() as usize)
+                .collect()
+        };
+        assert_eq!(ranks(2), vec![0, 2, 3, 1]);
+        assert_eq!(
+            ranks(4),
+            vec![0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5]
+        );
+
+        // Every threshold of the 8x8 matrix is used exactly once.
+        let mut all = ranks(BAYER_SIZE);
+        all.sort_unstable();
+        assert_eq!(all, (0..BAYER_SIZE * BAYER_SIZE).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn floyd_steinberg_keeps_mean() {
+        let (width, height) = (64, 64);
+        let mut quantizer = Quantizer::new(DitherMethod::FloydSteinberg, width, 0);
+        let gray = Srgba::new(0.5, 0.5, 0.5, 1.0);
+        let mut sum = [0u32; 3];
+        for y in 0..height {
+            for x in 0..width {
+                let pix = quantizer.quantize(x, y, gray);
+                for c in 0..3 {
+                    sum[c] += pix[c] as u32;
+                }
+            }
+        }
+        for &s in &sum {
+            let mean = s as f32 / (width * height) as f32;
+            assert!((mean - 127.5).abs() < 0.05, "{}", mean);
+        }
+    }
+
+    #[test]
+    fn floyd_steinberg_skips_rows() {
+        let mut quantizer = Quantizer::new(DitherMethod::FloydSteinberg, 8, 0);
+        let gray = Srgba::new(0.5, 0.5, 0.5, 1.0);
+        (0..8).for_each(|x| {
+            quantizer.quantize(x, 0, gray);
+        });
+        // Nothing of the first row reaches the third.
+        quantizer.quantize(0, 2, Srgba::new(0.0, 0.0, 0.0, 1.0));
+        assert!(quantizer.errors.iter().flatten().all(|&e| e == [0.0; 3]));
+    }
+
+    #[test]
+    fn floyd_steinberg_skips_transparent_pixels() {
+        let mut quantizer = Quantizer::new(DitherMethod::FloydSteinberg, 8, 0);
+        quantizer.quantize(0, 0, Srgba::new(0.5, 0.5, 0.5, 1.0));
+        quantizer.quantize(1, 0, Srgba::new(0.5, 0.5, 0.5, 0.0));
+        // The error of the first pixel stops at the transparent one.
+        assert_eq!(quantizer.errors[0][2], [0.0; 3]);
+        assert_eq!(quantizer.errors[0][3], [0.0; 3]);
+        assert_eq!(quantizer.errors[1][3], [0.0; 3]);
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Easing curves which shape the interpolation between two stops.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    Smoothstep,
+    EaseInQuad,
+    EaseOutQuad,
+    EaseInOutQuad,
+    EaseInCubic,
+    EaseOutCubic,
+    EaseInOutCubic,
+    EaseInSine,
+    EaseOutSine,
+    EaseInOutSine,
+    /// A CSS cubic Bézier curve through (0, 0), (x1, y1), (x2, y2) and (1, 1).
+    CubicBezier(f32, f32, f32, f32),
+    /// Jumps to the end color at the start of the segment.
+    StepStart,
+    /// Holds the start color until the end of the segment.
+    StepEnd,
+}
+
+impl Easing {
+    /// Maps `t` in [0.0, 1.0] to the eased factor.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        let half_pi = core::f32::consts::FRAC_PI_2;
+        match self {
+            Easing::Linear => t,
+            Easing::Smoothstep => t * t * (3.0 - 2.0 * t),
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - 2.0 * (1.0 - t) * (1.0 - t)
+                }
+            }
+            Easing::EaseInCubic => t * t * t,
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - 4.0 * (1.0 - t).powi(3)
+                }
+            }
+            Easing::EaseInSine => 1.0 - (t * half_pi).cos(),
+            Easing::EaseOutSine => (t * half_pi).sin(),
+            Easing::EaseInOutSine => 0.5 - 0.5 * (t * core::f32::consts::PI).cos(),
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+            Easing::StepStart => {
+                if t > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Easing::StepEnd => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+/// Evaluates one coordinate of a cubic Bézier curve with end points at 0 and 1.
+fn bezier(p1: f32, p2: f32, s: f32) -> f32 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * s * p1 + 3.0 * inv * s * s * p2 + s * s * s
+}
+
+/// Solves the curve for the parameter where x equals `t` and returns y there.
+/// Since x1 and x2 are within [0, 1], x is monotonic and bisection converges.
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, t: f32) -> f32 {
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    let mut s = t;
+    for _ in 0..32 {
+        let x = bezier(x1, x2, s);
+        if (x - t).abs() < 1e-6 {
+            break;
+        }
+        if x < t {
+            lo = s;
+        } else {
+            hi = s;
+        }
+        s = 0.5 * (lo + hi);
+    }
+    bezier(y1, y2, s)
+}
+
+impl fmt::Display for Easing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Easing::Linear => write!(f, "linear"),
+            Easing::Smoothstep => write!(f, "smoothstep"),
+            Easing::EaseInQuad => write!(f, "ease-in-quad"),
+            Easing::EaseOutQuad => write!(f, "ease-out-quad"),
+            Easing::EaseInOutQuad => write!(f, "ease-in-out-quad"),
+            Easing::EaseInCubic => write!(f, "ease-in-cubic"),
+            Easing::EaseOutCubic => write!(f, "ease-out-cubic"),
+            Easing::EaseInOutCubic => write!(f, "ease-in-out-cubic"),
+            Easing::EaseInSine => write!(f, "ease-in-sine"),
+            Easing::EaseOutSine => write!(f, "ease-out-sine"),
+            Easing::EaseInOutSine => write!(f, "ease-in-out-sine"),
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                write!(f, "cubic-bezier({},{},{},{})", x1, y1, x2, y2)
+            }
+            Easing::StepStart => write!(f, "step-start"),
+            Easing::StepEnd => write!(f, "step-end"),
+        }
+    }
+}
+
+impl FromStr for Easing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        let easing = match name.as_str() {
+            "linear" => Easing::Linear,
+            "smoothstep" => Easing::Smoothstep,
+            "ease-in-quad" => Easing::EaseInQuad,
+            "ease-out-quad" => Easing::EaseOutQuad,
+            "ease-in-out-quad" => Easing::EaseInOutQuad,
+            "ease-in-cubic" => Easing::EaseInCubic,
+            "ease-out-cubic" => Easing::EaseOutCubic,
+            "ease-in-out-cubic" => Easing::EaseInOutCubic,
+            "ease-in-sine" => Easing::EaseInSine,
+            "ease-out-sine" => Easing::EaseOutSine,
+            "ease-in-out-sine" => Easing::EaseInOutSine,
+            // The CSS keywords are shorthands for cubic Bézier curves.
+            "ease" => Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+            "ease-in" => Easing::CubicBezier(0.42, 0.0, 1.0, 1.0),
+            "ease-out" => Easing::CubicBezier(0.0, 0.0, 0.58, 1.0),
+            "ease-in-out" => Easing::CubicBezier(0.42, 0.0, 0.58, 1.0),
+            "step-start" => Easing::StepStart,
+            "step-end" => Easing::StepEnd,
+            _ if name.starts_with("cubic-bezier(") && name.ends_with(')') => {
+                let args = &name["cubic-bezier(".len()..name.len() - 1];
+                let values = args
+                    .split(',')
+                    .map(|v| v.trim().parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| format!("Could not parse the numbers in `{}`", s))?;
+                if values.len() != 4 {
+                    return Err(format!("`{}` should have 4 numbers", s));
+                }
+                if values[0] < 0.0 || values[0] > 1.0 || values[2] < 0.0 || values[2] > 1.0 {
+                    return Err(format!("x1 and x2 of `{}` should be from 0 to 1", s));
+                }
+                Easing::CubicBezier(values[0], values[1], values[2], values[3])
+            }
+            _ => return Err(format!("Unsupported easing `{}`", s)),
+        };
+        Ok(easing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn cubic_bezier_end_points() {
+        for &(x1, y1, x2, y2) in &[
+            (0.25, 0.1, 0.25, 1.0),
+            (0.42, 0.0, 0.58, 1.0),
+            (0.0, 1.5, 1.0, -0.5),
+        ] {
+            assert_close(cubic_bezier(x1, y1, x2, y2, 0.0), 0.0);
+            assert_close(cubic_bezier(x1, y1, x2, y2, 1.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn cubic_bezier_linear() {
+        for i in 0..=10 {
+            let t = i as f32 / 10.0;
+            assert_close(cubic_bezier(0.0, 0.0, 1.0, 1.0, t), t);
+            assert_close(cubic_bezier(0.3, 0.3, 0.7, 0.7, t), t);
+        }
+    }
+
+    #[test]
+    fn cubic_bezier_css_keywords() {
+        // Reference values of the CSS `ease`, `ease-in` and `ease-in-out`
+        // timing functions.
+        assert_close(Easing::from_str("ease").unwrap().apply(0.5), 0.8024);
+        assert_close(Easing::from_str("ease-in").unwrap().apply(0.5), 0.3154);
+        let ease_in_out = Easing::from_str("ease-in-out").unwrap();
+        assert_close(ease_in_out.apply(0.25), 0.1292);
+        assert_close(ease_in_out.apply(0.5), 0.5);
+        assert_close(ease_in_out.apply(0.75), 0.8708);
+    }
+
+    #[test]
+    fn cubic_bezier_parse() {
+        assert_eq!(
+            Easing::from_str("cubic-bezier(0.4, 0, 0.2, 1)").unwrap(),
+            Easing::CubicBezier(0.4, 0.0, 0.2, 1.0)
+        );
+        assert!(Easing::from_str("cubic-bezier(1.2, 0, 0.2, 1)").is_err());
+        assert!(Easing::from_str("cubic-bezier(0.4, 0, 0.2)").is_err());
+    }
+}
@@ -1,12 +1,22 @@
 use std::error::Error;
 use std::fmt;
+
+use crate::ColorError;
+
 #[derive(Debug)]
 pub enum CliError {
+    Color(ColorError),
     File(std::io::Error),
     Parse(std::num::ParseIntError),
     Time(std::time::SystemTimeError),
 }
 
+impl From<ColorError> for CliError {
+    fn from(err: ColorError) -> CliError {
+        CliError::Color(err)
+    }
+}
+
 impl From<std::io::Error> for CliError {
     fn from(err: std::io::Error) -> CliError {
         CliError::File(err)
@@ -28,6 +38,7 @@ impl From<std::time::SystemTimeError> for CliError {
 impl fmt::Display for CliError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            CliError::Color(ref err) => write!(f, "Color error: {}", err),
             CliError::File(ref err) => write!(f, "File error: {}", err),
             CliError::Parse(ref err) => write!(f, "Parse error: {}", err),
             CliError::Time(ref err) => write!(f, "Time error: {}", err),
@@ -38,6 +49,7 @@ impl fmt::Display for CliError {
 impl Error for CliError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
+            CliError::Color(err) => Some(err),
             CliError::File(err) => Some(err),
             CliError::Parse(err) => Some(err),
             CliError::Time(err) => Some(err),
@@ -0,0 +1,276 @@
+use std::cmp::Reverse;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use palette::white_point::D65;
+use palette::{Lab, Lcha, Srgb};
+
+use super::rng::SplitMix64;
+use crate::ColorStop;
+
+/// Most pixels clustered, larger images are sampled evenly.
+const MAX_SAMPLES: usize = 100_000;
+
+/// Most k-means iterations, unless clusters settle earlier.
+const MAX_ITERATIONS: usize = 50;
+
+/// The clustering used to find the dominant colors of an image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtractMethod {
+    /// k-means seeded with k-means++.
+    KMeans,
+    /// Median cut, splitting the box with the widest range at its median.
+    MedianCut,
+}
+
+impl fmt::Display for ExtractMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExtractMethod::KMeans => write!(f, "kmeans"),
+            ExtractMethod::MedianCut => write!(f, "median-cut"),
+        }
+    }
+}
+
+impl FromStr for ExtractMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "kmeans" | "k-means" => Ok(ExtractMethod::KMeans),
+            "median-cut" | "mediancut" => Ok(ExtractMethod::MedianCut),
+            _ => Err(format!("Unsupported extraction method `{}`", s)),
+        }
+    }
+}
+
+/// The order of the extracted colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtractSort {
+    /// Most common colors first.
+    Population,
+    /// Increasing `Lch` hue.
+    Hue,
+}
+
+impl fmt::Display for ExtractSort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExtractSort::Population => write!(f, "population"),
+            ExtractSort::Hue => write!(f, "hue"),
+        }
+    }
+}
+
+impl FromStr for ExtractSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "population" => Ok(ExtractSort::Population),
+            "hue" => Ok(ExtractSort::Hue),
+            _ => Err(format!("Unsupported extraction order `{}`", s)),
+        }
+    }
+}
+
+fn dist_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+/// The mean of `pixels`, or `None` for an empty set.
+fn mean<'a, I>(pixels: I) -> Option<[f32; 3]>
+where
+    I: IntoIterator<Item = &'a [f32; 3]>,
+{
+    let mut sum = [0.0; 3];
+    let mut count = 0;
+    for p in pixels {
+        (0..3).for_each(|i| sum[i] += p[i]);
+        count += 1;
+    }
+    if count == 0 {
+        return None;
+    }
+    Some([
+        sum[0] / count as f32,
+        sum[1] / count as f32,
+        sum[2] / count as f32,
+    ])
+}
+
+/// Clusters `pixels` into `count` colors with k-means, picking the initial
+/// centers with k-means++. Returns each center with its population.
+fn kmeans(pixels: &[[f32; 3]], count: usize, seed: u64) -> Vec<([f32; 3], usize)> {
+    let mut rng = SplitMix64(seed);
+
+    // k-means++ picks each further center with a probability proportional to
+    // its squared distance from the nearest center so far.
+    let first = (rng.next_f32() * pixels.len() as f32) as usize;
+    let mut centers = vec![pixels[first.min(pixels.len() - 1)]];
+    let mut nearest: Vec<f32> = pixels
+        .iter()
+        .map(|&p| dist_squared(p, centers[0]))
+        .collect();
+    while centers.len() < count {
+        let total: f32 = nearest.iter().sum();
+        if total <= 0.0 {
+            break;
+        }
+        let mut target = rng.next_f32() * total;
+        let index = nearest
+            .iter()
+            .position(|&d| {
+                target -= d;
+                target < 0.0
+            })
+            .unwrap_or(pixels.len() - 1);
+        let center = pixels[index];
+        nearest
+            .iter_mut()
+            .zip(pixels)
+            .for_each(|(n, &p)| *n = n.min(dist_squared(p, center)));
+        centers.push(center);
+    }
+
+    let mut labels = vec![usize::MAX; pixels.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (label, &p) in labels.iter_mut().zip(pixels) {
+            let closest = (0..centers.len())
+                .min_by(|&a, &b| {
+                    dist_squared(p, centers[a])
+                        .partial_cmp(&dist_squared(p, centers[b]))
+                        .unwrap()
+                })
+                .unwrap();
+            if *label != closest {
+                *label = closest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        for (k, center) in centers.iter_mut().enumerate() {
+            let members = pixels.iter().zip(&labels).filter(|&(_, &l)| l == k);
+            if let Some(m) = mean(members.map(|(p, _)| p)) {
+                *center = m;
+            }
+        }
+    }
+
+    let mut populations = vec![0; centers.len()];
+    labels.iter().for_each(|&l| populations[l] += 1);
+    centers.into_iter().zip(populations).collect()
+}
+
+/// Splits `pixels` into `count` boxes with median cut. Each box is summarized
+/// by its mean and population.
+fn median_cut(pixels: &[[f32; 3]], count: usize) -> Vec<([f32; 3], usize)> {
+    // The widest axis of a box and its range.
+    let widest = |pixels: &[[f32; 3]]| {
+        (0..3)
+            .map(|axis| {
+                let (min, max) = pixels.iter().fold((f32::MAX, f32::MIN), |(min, max), p| {
+                    (min.min(p[axis]), max.max(p[axis]))
+                });
+                (axis, max - min)
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .unwrap()
+    };
+
+    let mut boxes = vec![pixels.to_vec()];
+    while boxes.len() < count {
+        let (index, axis, range) = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                let (axis, range) = widest(b);
+                (i, axis, range)
+            })
+            .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
+            .unwrap();
+        if range <= 0.0 {
+            break;
+        }
+
+        let mut b = boxes.swap_remove(index);
+        b.sort_by(|p, q| p[axis].partial_cmp(&q[axis]).unwrap());
+        // Split at the change of value nearest the median, so equal colors
+        // stay in the same box.
+        let mid = b.len() / 2;
+        let split = (1..b.len())
+            .filter(|&i| b[i - 1][axis] < b[i][axis])
+            .min_by_key(|&i| (i as isize - mid as isize).abs())
+            .unwrap_or(mid);
+        let upper = b.split_off(split);
+        boxes.push(b);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .filter_map(|b| mean(b).map(|m| (m, b.len())))
+        .collect()
+}
+
+/// Finds the `count` dominant colors of the image at `path`, clustering its
+/// pixels in `Lab`. Transparent pixels are ignored. Clusters without pixels
+/// are dropped, so images with few distinct colors may yield fewer colors.
+pub fn extract_colors(
+    path: &Path,
+    count: usize,
+    method: ExtractMethod,
+    sort: ExtractSort,
+    seed: u64,
+) -> Result<Vec<ColorStop>, Box<dyn Error>> {
+    let img = image::open(path)
+        .map_err(|e| format!("Could not read image {}: {}", path.display(), e))?
+        .to_rgba8();
+
+    let opaque = img.pixels().filter(|p| p[3] > 0).count();
+    let stride = (opaque / MAX_SAMPLES).max(1);
+    let pixels: Vec<[f32; 3]> = img
+        .pixels()
+        .filter(|p| p[3] > 0)
+        .step_by(stride)
+        .map(|p| {
+            let rgb = Srgb::new(p[0], p[1], p[2]).into_format::<f32>();
+            let lab = Lab::<D65>::from(rgb.into_linear());
+            [lab.l, lab.a, lab.b]
+        })
+        .collect();
+    if pixels.is_empty() || count == 0 {
+        return Err(format!("No colors to extract from {}", path.display()).into());
+    }
+
+    let clusters = match method {
+        ExtractMethod::KMeans => kmeans(&pixels, count, seed),
+        ExtractMethod::MedianCut => median_cut(&pixels, count),
+    };
+    let mut colors: Vec<(Lcha<D65>, usize)> = clusters
+        .into_iter()
+        .filter(|&(_, population)| population > 0)
+        .map(|(c, population)| (Lcha::from(Lab::<D65>::new(c[0], c[1], c[2])), population))
+        .collect();
+
+    match sort {
+        ExtractSort::Population => colors.sort_by_key(|&(_, population)| Reverse(population)),
+        ExtractSort::Hue => colors.sort_by(|a, b| {
+            let hue = |c: &Lcha<D65>| c.hue.to_positive_degrees();
+            hue(&a.0).partial_cmp(&hue(&b.0)).unwrap()
+        }),
+    }
+
+    Ok(colors
+        .into_iter()
+        .map(|(color, _)| ColorStop {
+            color,
+            position: None,
+        })
+        .collect())
+}
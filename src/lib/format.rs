@@ -0,0 +1,181 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::str::FromStr;
+
+use image::{ColorType, ImageFormat};
+
+/// Image formats which can be written to disk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Bmp,
+    Farbfeld,
+    Ico,
+    Jpeg,
+    Png,
+    Pnm,
+    /// Vector output, only written by the linear and stepped gradients.
+    Svg,
+    Tga,
+    Tiff,
+}
+
+impl OutputFormat {
+    /// Infers the format from the extension of `path`.
+    pub fn from_path(path: &Path) -> Option<OutputFormat> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| ext.parse().ok())
+    }
+
+    /// The default file extension for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Bmp => "bmp",
+            OutputFormat::Farbfeld => "ff",
+            OutputFormat::Ico => "ico",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+            OutputFormat::Pnm => "pnm",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Tga => "tga",
+            OutputFormat::Tiff => "tiff",
+        }
+    }
+
+    /// Whether the format is able to store an alpha channel.
+    pub fn supports_alpha(self) -> bool {
+        !matches!(self, OutputFormat::Jpeg)
+    }
+
+    fn image_format(self) -> Option<ImageFormat> {
+        match self {
+            OutputFormat::Bmp => Some(ImageFormat::Bmp),
+            OutputFormat::Farbfeld => Some(ImageFormat::Farbfeld),
+            OutputFormat::Ico => Some(ImageFormat::Ico),
+            OutputFormat::Jpeg => Some(ImageFormat::Jpeg),
+            OutputFormat::Png => Some(ImageFormat::Png),
+            OutputFormat::Pnm => Some(ImageFormat::Pnm),
+            OutputFormat::Svg => None,
+            OutputFormat::Tga => Some(ImageFormat::Tga),
+            OutputFormat::Tiff => Some(ImageFormat::Tiff),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            OutputFormat::Bmp => "BMP",
+            OutputFormat::Farbfeld => "farbfeld",
+            OutputFormat::Ico => "ICO",
+            OutputFormat::Jpeg => "JPEG",
+            OutputFormat::Png => "PNG",
+            OutputFormat::Pnm => "PNM",
+            OutputFormat::Svg => "SVG",
+            OutputFormat::Tga => "TGA",
+            OutputFormat::Tiff => "TIFF",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bmp" => Ok(OutputFormat::Bmp),
+            "ff" | "farbfeld" => Ok(OutputFormat::Farbfeld),
+            "ico" => Ok(OutputFormat::Ico),
+            "jpg" | "jpeg" => Ok(OutputFormat::Jpeg),
+            "png" => Ok(OutputFormat::Png),
+            "pnm" | "pbm" | "pgm" | "ppm" | "pam" => Ok(OutputFormat::Pnm),
+            "svg" => Ok(OutputFormat::Svg),
+            "tga" => Ok(OutputFormat::Tga),
+            "tif" | "tiff" => Ok(OutputFormat::Tiff),
+            _ => Err(format!("Unsupported image format `{}`", s)),
+        }
+    }
+}
+
+/// Encodes an 8-bit `buf` of `color` type to `path` with the chosen format.
+pub(crate) fn write_buffer(
+    buf: &[u8],
+    width: u32,
+    height: u32,
+    color: ColorType,
+    path: &Path,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let has_alpha = color == ColorType::Rgba8;
+    if has_alpha && !format.supports_alpha() {
+        return Err(format!(
+            "{} cannot store an alpha channel, choose a format such as PNG or TIFF",
+            format
+        )
+        .into());
+    }
+
+    match format {
+        // PNM picks PPM for opaque images and PAM for images with alpha,
+        // regardless of the file extension.
+        OutputFormat::Pnm => {
+            let fout = BufWriter::new(File::create(path)?);
+            image::codecs::pnm::PnmEncoder::new(fout).encode(buf, width, height, color)?;
+        }
+        // Farbfeld only stores 16-bit RGBA.
+        OutputFormat::Farbfeld => {
+            let channels = if has_alpha { 4 } else { 3 };
+            let mut wide: Vec<u16> = Vec::with_capacity((width * height * 4) as usize);
+            for px in buf.chunks(channels) {
+                wide.extend(px.iter().map(|&c| u16::from(c) * 257));
+                if !has_alpha {
+                    wide.push(u16::MAX);
+                }
+            }
+            let imgbuf: image::ImageBuffer<image::Rgba<u16>, _> =
+                image::ImageBuffer::from_raw(width, height, wide)
+                    .ok_or("Image buffer has the wrong size")?;
+            imgbuf.save_with_format(path, ImageFormat::Farbfeld)?;
+        }
+        _ => {
+            let image_format = format.image_format().ok_or_else(|| {
+                format!(
+                    "{} output is only supported for linear gradients and stepped linear and radial gradients",
+                    format
+                )
+            })?;
+            image::save_buffer_with_format(path, buf, width, height, color, image_format)?
+        }
+    }
+
+    Ok(())
+}
+
+/// Encodes a 16-bit `buf` of `color` type to `path`. Only PNG is written
+/// with 16 bits per channel.
+pub(crate) fn write_buffer16(
+    buf: &[u16],
+    width: u32,
+    height: u32,
+    color: ColorType,
+    path: &Path,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    if format != OutputFormat::Png {
+        return Err(format!(
+            "{} output is only supported with 8 bits per channel",
+            format
+        )
+        .into());
+    }
+
+    // The encoder takes the samples as bytes in native byte order.
+    let bytes: Vec<u8> = buf.iter().flat_map(|v| v.to_ne_bytes().to_vec()).collect();
+    image::save_buffer_with_format(path, &bytes, width, height, color, ImageFormat::Png)?;
+
+    Ok(())
+}
@@ -0,0 +1,262 @@
+use std::cell::Cell;
+use std::fmt;
+use std::str::FromStr;
+
+use palette::white_point::D65;
+use palette::{Lcha, LinSrgb, LinSrgba, Srgba};
+
+use super::diff::delta_eok;
+use super::space::{Oklab, Oklch};
+use crate::Config;
+
+/// Linear channels this far outside [0.0, 1.0] still count as in gamut, so
+/// round trips through other spaces don't flag in-gamut colors.
+const EPSILON: f32 = 1e-4;
+
+/// How colors outside of sRGB are brought into it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamutMethod {
+    /// Clamp each channel, which can shift hue.
+    Clip,
+    /// Reduce `Lch` chroma at constant lightness and hue until in gamut.
+    Chroma,
+    /// The CSS Color 4 algorithm, reducing Oklch chroma while clipping within
+    /// a just noticeable difference.
+    Css,
+}
+
+impl GamutMethod {
+    /// Maps `color` into the sRGB gamut, keeping its alpha.
+    pub fn map(self, color: LinSrgba) -> LinSrgba {
+        if in_gamut(color.color) {
+            return clip(color);
+        }
+        let mapped = match self {
+            GamutMethod::Clip => color,
+            GamutMethod::Chroma => reduce_chroma(color),
+            GamutMethod::Css => css_map(color),
+        };
+        clip(mapped)
+    }
+}
+
+impl fmt::Display for GamutMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            GamutMethod::Clip => "clip",
+            GamutMethod::Chroma => "chroma",
+            GamutMethod::Css => "css",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for GamutMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "clip" => Ok(GamutMethod::Clip),
+            "chroma" => Ok(GamutMethod::Chroma),
+            "css" => Ok(GamutMethod::Css),
+            _ => Err(format!("Unsupported gamut mapping `{}`", s)),
+        }
+    }
+}
+
+pub(crate) fn in_gamut(color: LinSrgb) -> bool {
+    [color.red, color.green, color.blue]
+        .iter()
+        .all(|c| (-EPSILON..=1.0 + EPSILON).contains(c))
+}
+
+pub(crate) fn clip(color: LinSrgba) -> LinSrgba {
+    let c = |v: f32| v.clamp(0.0, 1.0);
+    LinSrgba::new(c(color.red), c(color.green), c(color.blue), c(color.alpha))
+}
+
+/// Binary searches the largest `Lch` chroma in gamut at the same lightness
+/// and hue.
+fn reduce_chroma(color: LinSrgba) -> LinSrgba {
+    let mut lch = Lcha::<D65>::from(color);
+    if lch.l >= 100.0 {
+        return LinSrgba::new(1.0, 1.0, 1.0, color.alpha);
+    } else if lch.l <= 0.0 {
+        return LinSrgba::new(0.0, 0.0, 0.0, color.alpha);
+    }
+
+    let (mut min, mut max) = (0.0, lch.chroma);
+    while max - min > 0.01 {
+        lch.chroma = 0.5 * (min + max);
+        if in_gamut(LinSrgba::from(lch).color) {
+            min = lch.chroma;
+        } else {
+            max = lch.chroma;
+        }
+    }
+    lch.chroma = min;
+    LinSrgba::from(lch)
+}
+
+/// The CSS Color 4 gamut mapping: binary search Oklch chroma, accepting the
+/// clipped color once it's within a just noticeable difference of the
+/// unclipped one.
+fn css_map(color: LinSrgba) -> LinSrgba {
+    const JND: f32 = 0.02;
+    const MIN_CHROMA_STEP: f32 = 0.0001;
+
+    let origin = Oklch::from(Oklab::from_linear(color.color));
+    if origin.l >= 1.0 {
+        return LinSrgba::new(1.0, 1.0, 1.0, color.alpha);
+    } else if origin.l <= 0.0 {
+        return LinSrgba::new(0.0, 0.0, 0.0, color.alpha);
+    }
+
+    let with_chroma = |chroma: f32| Oklab::from(Oklch { chroma, ..origin });
+    let clip_oklab = |lab: Oklab| {
+        let rgb = lab.into_linear();
+        Oklab::from_linear(clip(LinSrgba::new(rgb.red, rgb.green, rgb.blue, 1.0)).color)
+    };
+    let with_alpha = |lab: Oklab| {
+        let rgb = lab.into_linear();
+        LinSrgba::new(rgb.red, rgb.green, rgb.blue, color.alpha)
+    };
+
+    let mut current = with_chroma(origin.chroma);
+    let mut clipped = clip_oklab(current);
+    if delta_eok(clipped, current) < JND {
+        return with_alpha(clipped);
+    }
+
+    let (mut min, mut max) = (0.0, origin.chroma);
+    let mut min_in_gamut = true;
+    while max - min > MIN_CHROMA_STEP {
+        let chroma = 0.5 * (min + max);
+        current = with_chroma(chroma);
+        if min_in_gamut && in_gamut(current.into_linear()) {
+            min = chroma;
+            continue;
+        }
+        clipped = clip_oklab(current);
+        let e = delta_eok(clipped, current);
+        if e < JND {
+            if JND - e < MIN_CHROMA_STEP {
+                break;
+            }
+            min_in_gamut = false;
+            min = chroma;
+        } else {
+            max = chroma;
+        }
+    }
+    with_alpha(clipped)
+}
+
+/// Maps colors into sRGB for rendering and counts how many were out of gamut.
+pub(crate) struct Gamut {
+    method: GamutMethod,
+    total: Cell<usize>,
+    out: Cell<usize>,
+}
+
+impl Gamut {
+    pub(crate) fn new(config: &Config) -> Gamut {
+        Gamut {
+            method: config.gamut,
+            total: Cell::new(0),
+            out: Cell::new(0),
+        }
+    }
+
+    /// Maps `color` into gamut without counting it.
+    pub(crate) fn map(&self, color: LinSrgba) -> LinSrgba {
+        self.method.map(color)
+    }
+
+    /// Maps `color` into gamut and converts it to sRGB, counting it once.
+    pub(crate) fn to_srgb(&self, color: LinSrgba) -> Srgba {
+        self.to_srgb_n(color, 1)
+    }
+
+    /// Maps `color` into gamut and converts it to sRGB, counting it `n`
+    /// times for colors which fill several pixels.
+    pub(crate) fn to_srgb_n(&self, color: LinSrgba, n: usize) -> Srgba {
+        self.total.set(self.total.get() + n);
+        if !in_gamut(color.color) {
+            self.out.set(self.out.get() + n);
+        }
+        Srgba::from_linear(self.map(color))
+    }
+
+    /// Reports how many of the counted `unit`s were out of gamut on stderr,
+    /// if `enabled`.
+    pub(crate) fn report(&self, enabled: bool, unit: &str) {
+        if enabled {
+            eprintln!(
+                "{} of {} {} were out of gamut, mapped with {}",
+                self.out.get(),
+                self.total.get(),
+                unit,
+                self.method
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oklch(color: LinSrgba) -> Oklch {
+        Oklch::from(Oklab::from_linear(color.color))
+    }
+
+    #[test]
+    fn css_map_keeps_colors_in_gamut() {
+        for &(r, g, b) in &[
+            (0.0, 0.0, 0.0),
+            (0.8, 0.2, 0.05),
+            (0.1, 0.5, 0.9),
+            (1.0, 1.0, 1.0),
+        ] {
+            let color = LinSrgba::new(r, g, b, 0.5);
+            let mapped = css_map(color);
+            for &(c, m) in &[
+                (color.red, mapped.red),
+                (color.green, mapped.green),
+                (color.blue, mapped.blue),
+                (color.alpha, mapped.alpha),
+            ] {
+                assert!((c - m).abs() < 1e-3, "{:?} != {:?}", color, mapped);
+            }
+        }
+    }
+
+    #[test]
+    fn css_map_reduces_chroma() {
+        // Saturated colors of Display P3 and beyond, outside of sRGB.
+        for &(r, g, b) in &[(1.2, -0.1, 0.3), (-0.2, 1.1, 0.1), (0.05, 0.1, 1.4)] {
+            let color = LinSrgba::new(r, g, b, 0.75);
+            let mapped = css_map(color);
+            assert!(in_gamut(mapped.color), "{:?}", mapped);
+            assert_eq!(mapped.alpha, 0.75);
+
+            // Lightness is kept and chroma reduced, within the just noticeable
+            // difference the mapping allows.
+            let (origin, result) = (oklch(color), oklch(mapped));
+            assert!(
+                (origin.l - result.l).abs() < 0.02,
+                "{:?} {:?}",
+                origin,
+                result
+            );
+            assert!(result.chroma < origin.chroma, "{:?} {:?}", origin, result);
+        }
+    }
+
+    #[test]
+    fn css_map_extreme_lightness() {
+        let white = css_map(LinSrgba::new(2.0, 1.5, 1.5, 1.0));
+        assert_eq!(white, LinSrgba::new(1.0, 1.0, 1.0, 1.0));
+    }
+}
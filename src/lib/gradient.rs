@@ -0,0 +1,535 @@
+use std::fmt;
+use std::str::FromStr;
+
+use palette::white_point::D65;
+use palette::{Alpha, Gradient, Hsla, Hsva, Lab, Laba, Lcha, LinSrgba, Mix};
+
+use super::diff::{delta_e2000, delta_eok};
+use super::space::{GammaRgb, Oklab, Oklch, SpaceColor};
+use crate::{ColorStop, Config, Easing, Space};
+
+/// The direction hue is interpolated in between two colors of a cylindrical
+/// color space, following the CSS Color 4 hue interpolation methods.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HueMethod {
+    /// Take the arc of 180 degrees or less.
+    Shorter,
+    /// Take the arc of 180 degrees or more.
+    Longer,
+    /// Go counterclockwise, with hue increasing.
+    Increasing,
+    /// Go clockwise, with hue decreasing.
+    Decreasing,
+}
+
+impl HueMethod {
+    /// The signed difference in degrees to travel from `from` to `to`.
+    pub fn hue_diff(self, from: f32, to: f32) -> f32 {
+        let diff = (to - from).rem_euclid(360.0);
+        match self {
+            HueMethod::Shorter if diff > 180.0 => diff - 360.0,
+            HueMethod::Shorter => diff,
+            HueMethod::Longer if diff > 0.0 && diff < 180.0 => diff - 360.0,
+            HueMethod::Longer if diff == 0.0 => 360.0,
+            HueMethod::Longer => diff,
+            HueMethod::Increasing => diff,
+            HueMethod::Decreasing if diff > 0.0 => diff - 360.0,
+            HueMethod::Decreasing => diff,
+        }
+    }
+}
+
+impl fmt::Display for HueMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            HueMethod::Shorter => "shorter",
+            HueMethod::Longer => "longer",
+            HueMethod::Increasing => "increasing",
+            HueMethod::Decreasing => "decreasing",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for HueMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "shorter" => Ok(HueMethod::Shorter),
+            "longer" => Ok(HueMethod::Longer),
+            "increasing" => Ok(HueMethod::Increasing),
+            "decreasing" => Ok(HueMethod::Decreasing),
+            _ => Err(format!("Unsupported hue interpolation method `{}`", s)),
+        }
+    }
+}
+
+/// Number of samples used to measure the length of a gradient for
+/// perceptual step spacing.
+const ARC_SAMPLES: usize = 2048;
+
+/// How the steps of a stepped gradient are spaced.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepSpacing {
+    /// Evenly in the gradient's parameter.
+    Even,
+    /// By equal CIEDE2000 differences along the gradient.
+    De2000,
+    /// By equal Euclidean distances in `Oklab` along the gradient.
+    Oklab,
+}
+
+impl StepSpacing {
+    /// The difference between two colors in the metric of the spacing. Even
+    /// spacing measures CIEDE2000.
+    pub fn distance(self, a: LinSrgba, b: LinSrgba) -> f32 {
+        match self {
+            StepSpacing::Even | StepSpacing::De2000 => {
+                delta_e2000(Lab::from(a.color), Lab::from(b.color))
+            }
+            StepSpacing::Oklab => {
+                delta_eok(Oklab::from_linear(a.color), Oklab::from_linear(b.color))
+            }
+        }
+    }
+
+    /// Prints the difference between each pair of adjacent steps on stderr,
+    /// unless the steps are spaced evenly.
+    pub(crate) fn report(self, steps: &[LinSrgba]) {
+        let (name, precision) = match self {
+            StepSpacing::Even => return,
+            StepSpacing::De2000 => ("ΔE2000", 2),
+            StepSpacing::Oklab => ("ΔEOK", 4),
+        };
+        let differences: Vec<String> = steps
+            .windows(2)
+            .map(|pair| format!("{:.*}", precision, self.distance(pair[0], pair[1])))
+            .collect();
+        eprintln!("{} between steps: {}", name, differences.join(", "));
+    }
+}
+
+impl fmt::Display for StepSpacing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            StepSpacing::Even => "even",
+            StepSpacing::De2000 => "de2000",
+            StepSpacing::Oklab => "oklab",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for StepSpacing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "even" => Ok(StepSpacing::Even),
+            "de2000" | "ciede2000" => Ok(StepSpacing::De2000),
+            "oklab" => Ok(StepSpacing::Oklab),
+            _ => Err(format!("Unsupported step spacing `{}`", s)),
+        }
+    }
+}
+
+/// Fills in missing stop positions and checks that they increase. Missing
+/// first and last positions are 0.0 and 1.0, other missing positions are
+/// spaced evenly between their known neighbors.
+pub fn resolve_positions(positions: &[Option<f32>]) -> Result<Vec<f32>, String> {
+    let mut resolved: Vec<f32> = Vec::with_capacity(positions.len());
+    let last = positions.len().saturating_sub(1);
+    let mut i = 0;
+    while i < positions.len() {
+        let known = match positions[i] {
+            Some(p) => Some(p),
+            None if i == 0 => Some(0.0),
+            None if i == last => Some(1.0),
+            None => None,
+        };
+        if let Some(p) = known {
+            resolved.push(p);
+            i += 1;
+            continue;
+        }
+
+        // Spread the run of missing positions up to the next known position.
+        let start = resolved[i - 1];
+        let next = (i..positions.len())
+            .find(|&j| positions[j].is_some() || j == last)
+            .unwrap_or(last);
+        let end = positions[next].unwrap_or(1.0);
+        let count = (next - i + 1) as f32;
+        for k in 0..(next - i) {
+            resolved.push(start + (end - start) * (k + 1) as f32 / count);
+        }
+        i = next;
+    }
+
+    for (i, pair) in resolved.windows(2).enumerate() {
+        if pair[1] <= pair[0] {
+            return Err(format!(
+                "Stop positions must increase, color {} at {} is not after {}",
+                i + 2,
+                pair[1],
+                pair[0]
+            ));
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Closes the circle of a radial gradient with a stop of the first color at
+/// 1.0, after the last stop. A last stop already at 1.0 closes the circle
+/// itself, so positions are only checked against the stops that were given.
+pub fn wrap_stops(stops: &mut Vec<ColorStop>) {
+    match (stops.first(), stops.last()) {
+        (Some(_), Some(last)) if last.position == Some(1.0) => {}
+        (Some(&first), _) => stops.push(ColorStop {
+            color: first.color,
+            position: Some(1.0),
+        }),
+        _ => {}
+    }
+}
+
+/// Sampling of a gradient independent of the space it's interpolated in.
+trait Interpolate {
+    fn get(&self, i: f32) -> LinSrgba;
+}
+
+/// A color which the gradient mixes in premultiplied alpha, so translucent
+/// stops don't tint their neighbors.
+#[derive(Clone)]
+struct Premultiplied<C>(C);
+
+impl<C: SpaceColor> Mix for Premultiplied<C> {
+    type Scalar = f32;
+
+    fn mix(&self, other: &Self, factor: f32) -> Self {
+        Premultiplied(self.0.mix_premultiplied(&other.0, factor))
+    }
+}
+
+impl<C: SpaceColor> Interpolate for Gradient<Premultiplied<C>> {
+    fn get(&self, i: f32) -> LinSrgba {
+        Gradient::get(self, i).0.into_linear()
+    }
+}
+
+/// A gradient through the input colors, interpolated in a chosen color space
+/// with premultiplied alpha. Colors are returned in linear sRGB for rendering.
+pub struct ColorGradient {
+    inner: Box<dyn Interpolate>,
+    positions: Vec<f32>,
+    easing: Vec<Easing>,
+}
+
+impl ColorGradient {
+    /// Creates a gradient of the colors in `config.grad_vec` placed at
+    /// `config.positions`, interpolated in `config.space`.
+    pub fn new(config: &Config) -> ColorGradient {
+        ColorGradient::with_stops(
+            config.space,
+            &config.grad_vec,
+            &config.positions,
+            &config.hue,
+            &config.easing,
+        )
+    }
+
+    /// Creates a gradient of `colors` placed at `positions`, interpolated in
+    /// `space` with the hue methods and easing curves of its segments.
+    fn with_stops(
+        space: Space,
+        colors: &[Lcha<D65>],
+        positions: &[f32],
+        hue: &[HueMethod],
+        easing: &[Easing],
+    ) -> ColorGradient {
+        match space {
+            Space::Hsl => ColorGradient::in_space::<Hsla>(colors, positions, hue, easing),
+            Space::Hsv => ColorGradient::in_space::<Hsva>(colors, positions, hue, easing),
+            Space::Lab => ColorGradient::in_space::<Laba<D65>>(colors, positions, hue, easing),
+            Space::Lch => ColorGradient::in_space::<Lcha<D65>>(colors, positions, hue, easing),
+            Space::LinearRgb => ColorGradient::in_space::<LinSrgba>(colors, positions, hue, easing),
+            Space::Oklab => {
+                ColorGradient::in_space::<Alpha<Oklab, f32>>(colors, positions, hue, easing)
+            }
+            Space::Oklch => {
+                ColorGradient::in_space::<Alpha<Oklch, f32>>(colors, positions, hue, easing)
+            }
+            Space::Srgb => {
+                ColorGradient::in_space::<Alpha<GammaRgb, f32>>(colors, positions, hue, easing)
+            }
+        }
+    }
+
+    /// Builds the gradient in the space of `C`. `hue` holds either one method
+    /// for every segment or one method per segment.
+    fn in_space<C: SpaceColor + 'static>(
+        colors: &[Lcha<D65>],
+        positions: &[f32],
+        hue: &[HueMethod],
+        easing: &[Easing],
+    ) -> ColorGradient {
+        let colors: Vec<C> = colors.iter().map(|&c| C::from_lcha(c)).collect();
+
+        let mut points = Vec::with_capacity(colors.len());
+        points.push((positions[0], Premultiplied(colors[0].clone())));
+        for (i, pair) in colors.windows(2).enumerate() {
+            let (from, to) = (&pair[0], &pair[1]);
+            let (start, end) = (positions[i], positions[i + 1]);
+            let method = hue.get(i).or_else(|| hue.first());
+
+            // palette always mixes hue along the shorter arc, so the segment
+            // is split into arcs of less than 180 degrees which together
+            // follow the chosen direction. Segments to or from a transparent
+            // color keep the hue of the other end instead.
+            let method = method.filter(|_| from.alpha() > 0.0 && to.alpha() > 0.0);
+            if let (Some(&method), Some(h0), Some(h1)) = (method, from.hue(), to.hue()) {
+                let diff = method.hue_diff(h0, h1);
+                let pieces = (diff.abs() / 120.0).ceil().max(1.0) as usize;
+                for k in 1..pieces {
+                    let factor = k as f32 / pieces as f32;
+                    let mut color = from.mix_premultiplied(to, factor);
+                    color.set_hue(h0 + diff * factor);
+                    points.push((start + factor * (end - start), Premultiplied(color)));
+                }
+            }
+            points.push((end, Premultiplied(to.clone())));
+        }
+
+        ColorGradient {
+            inner: Box::new(Gradient::with_domain(points)),
+            positions: positions.to_vec(),
+            easing: easing.to_vec(),
+        }
+    }
+
+    /// Gets the color at `i` in the domain [0.0, 1.0].
+    pub fn get(&self, i: f32) -> LinSrgba {
+        self.inner.get(self.ease(i))
+    }
+
+    /// The positions of the input stops.
+    pub fn positions(&self) -> &[f32] {
+        &self.positions
+    }
+
+    /// Remaps `i` within its segment by the segment's easing curve.
+    fn ease(&self, i: f32) -> f32 {
+        let segment = match self.positions.windows(2).position(|p| i < p[1]) {
+            Some(segment) if i > self.positions[0] => segment,
+            _ => return i,
+        };
+        let easing = match self.easing.get(segment).or_else(|| self.easing.first()) {
+            Some(&Easing::Linear) | None => return i,
+            Some(&easing) => easing,
+        };
+
+        let (start, end) = (self.positions[segment], self.positions[segment + 1]);
+        start + easing.apply((i - start) / (end - start)) * (end - start)
+    }
+
+    /// Takes `n` evenly spaced colors from the domain [0.0, 1.0], including
+    /// both ends, regardless of where the first and last stops are placed.
+    pub fn take(&self, n: usize) -> Vec<LinSrgba> {
+        let last = n.saturating_sub(1).max(1) as f32;
+        (0..n).map(|i| self.get(i as f32 / last)).collect()
+    }
+
+    /// Takes `n` colors from the domain [0.0, 1.0], including both ends,
+    /// spaced by `spacing`. For perceptual spacing, the gradient is
+    /// reparameterized by its cumulative length in that metric, so adjacent
+    /// steps are equally far apart.
+    pub fn take_spaced(&self, n: usize, spacing: StepSpacing) -> Vec<LinSrgba> {
+        if spacing == StepSpacing::Even {
+            return self.take(n);
+        }
+
+        let samples = ARC_SAMPLES.max(n * 16);
+        let mut lengths = Vec::with_capacity(samples + 1);
+        lengths.push(0.0);
+        let mut previous = self.get(0.0);
+        for i in 1..=samples {
+            let color = self.get(i as f32 / samples as f32);
+            lengths.push(lengths[i - 1] + spacing.distance(previous, color));
+            previous = color;
+        }
+        let total = lengths[samples];
+        if total <= 0.0 {
+            return self.take(n);
+        }
+
+        let last = n.saturating_sub(1).max(1) as f32;
+        (0..n)
+            .map(|k| {
+                // Find the sample interval holding the target length and
+                // interpolate the parameter within it.
+                let target = total * k as f32 / last;
+                let j = lengths
+                    .partition_point(|&l| l <= target)
+                    .max(1)
+                    .min(samples);
+                let span = lengths[j] - lengths[j - 1];
+                let within = if span > 0.0 {
+                    (target - lengths[j - 1]) / span
+                } else {
+                    0.0
+                };
+                self.get((j - 1) as f32 / samples as f32 + within / samples as f32)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use palette::Srgba;
+
+    fn gradient(space: Space, colors: &[Lcha<D65>]) -> ColorGradient {
+        let last = (colors.len() - 1) as f32;
+        let positions: Vec<f32> = (0..colors.len()).map(|i| i as f32 / last).collect();
+        ColorGradient::with_stops(space, colors, &positions, &[], &[])
+    }
+
+    fn assert_close(a: LinSrgba, b: LinSrgba) {
+        let diff = [
+            a.red - b.red,
+            a.green - b.green,
+            a.blue - b.blue,
+            a.alpha - b.alpha,
+        ];
+        assert!(diff.iter().all(|d| d.abs() < 1e-4), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn take_includes_both_ends() {
+        let colors = [
+            Lcha::new(0.0, 0.0, 0.0, 1.0),
+            Lcha::new(60.0, 40.0, 30.0, 1.0),
+            Lcha::new(100.0, 0.0, 0.0, 1.0),
+        ];
+        for &space in &[Space::Lch, Space::Srgb, Space::Oklab] {
+            let grad = gradient(space, &colors);
+            for &n in &[2, 3, 7] {
+                let steps = grad.take(n);
+                assert_eq!(steps.len(), n);
+                assert_close(steps[0], LinSrgba::from(colors[0]));
+                assert_close(steps[n - 1], LinSrgba::from(colors[2]));
+            }
+        }
+    }
+
+    #[test]
+    fn take_one() {
+        let colors = [
+            Lcha::new(20.0, 0.0, 0.0, 1.0),
+            Lcha::new(80.0, 0.0, 0.0, 1.0),
+        ];
+        let steps = gradient(Space::Lch, &colors).take(1);
+        assert_eq!(steps.len(), 1);
+        assert_close(steps[0], LinSrgba::from(colors[0]));
+    }
+
+    #[test]
+    fn take_spaced_includes_both_ends() {
+        let colors = [
+            Lcha::new(50.0, 80.0, 30.0, 1.0),
+            Lcha::new(90.0, 70.0, 100.0, 1.0),
+            Lcha::new(30.0, 60.0, 300.0, 1.0),
+        ];
+        let grad = gradient(Space::Lch, &colors);
+        for &spacing in &[StepSpacing::De2000, StepSpacing::Oklab] {
+            let steps = grad.take_spaced(5, spacing);
+            assert_eq!(steps.len(), 5);
+            assert_close(steps[0], LinSrgba::from(colors[0]));
+            assert_close(steps[4], LinSrgba::from(colors[2]));
+        }
+    }
+
+    #[test]
+    fn transparent_stops_take_the_other_hue() {
+        let red = Lcha::from(Srgba::new(1.0, 0.0, 0.0, 0.0).into_linear());
+        let blue = Lcha::from(Srgba::new(0.0, 0.0, 1.0, 1.0).into_linear());
+        for &space in &[Space::Lch, Space::Oklch, Space::Hsl] {
+            let steps = gradient(space, &[red, blue]).take(5);
+            for (i, step) in steps.iter().enumerate().skip(1) {
+                let expected = Srgba::new(0.0, 0.0, 1.0, i as f32 / 4.0).into_linear();
+                assert_close(*step, expected);
+            }
+            let steps = gradient(space, &[blue, red]).take(5);
+            assert_close(steps[2], Srgba::new(0.0, 0.0, 1.0, 0.5).into_linear());
+        }
+    }
+
+    fn assert_positions(positions: &[Option<f32>], expected: &[f32]) {
+        let resolved = resolve_positions(positions).unwrap();
+        assert_eq!(resolved.len(), expected.len());
+        for (a, b) in resolved.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-6, "{:?} != {:?}", resolved, expected);
+        }
+    }
+
+    #[test]
+    fn resolve_missing_positions() {
+        assert_positions(&[None], &[0.0]);
+        assert_positions(&[None, None], &[0.0, 1.0]);
+        assert_positions(&[None, None, None], &[0.0, 0.5, 1.0]);
+        assert_positions(
+            &[None, Some(0.2), None, None, Some(0.8)],
+            &[0.0, 0.2, 0.4, 0.6, 0.8],
+        );
+        assert_positions(&[Some(0.1), None, Some(0.9)], &[0.1, 0.5, 0.9]);
+        assert_positions(&[None, Some(0.7), None], &[0.0, 0.7, 1.0]);
+    }
+
+    #[test]
+    fn resolve_rejects_decreasing_positions() {
+        assert!(resolve_positions(&[Some(0.5), Some(0.5)]).is_err());
+        assert!(resolve_positions(&[None, Some(0.6), Some(0.3), None]).is_err());
+        // The missing last position defaults to 1.0, before an explicit 0.0.
+        assert!(resolve_positions(&[Some(1.0), None]).is_err());
+    }
+
+    fn wrapped_positions(positions: &[Option<f32>]) -> Result<Vec<f32>, String> {
+        let mut stops: Vec<ColorStop> = positions
+            .iter()
+            .map(|&position| ColorStop {
+                color: Lcha::new(50.0, 0.0, 0.0, 1.0),
+                position,
+            })
+            .collect();
+        wrap_stops(&mut stops);
+        resolve_positions(&stops.iter().map(|s| s.position).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn wrap_spaces_stops_around_the_circle() {
+        let resolved = wrapped_positions(&[None, None, None]).unwrap();
+        assert_eq!(resolved.len(), 4);
+        assert!((resolved[1] - 1.0 / 3.0).abs() < 1e-6);
+        assert!((resolved[2] - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(resolved[3], 1.0);
+    }
+
+    #[test]
+    fn wrap_keeps_a_last_stop_at_one() {
+        assert_eq!(
+            wrapped_positions(&[Some(0.0), Some(0.7), Some(1.0)]).unwrap(),
+            vec![0.0, 0.7, 1.0]
+        );
+        assert_eq!(
+            wrapped_positions(&[Some(0.0), Some(0.7), None]).unwrap(),
+            vec![0.0, 0.7, 0.85, 1.0]
+        );
+        // Errors name the given stops, never the closing one.
+        let err = wrapped_positions(&[Some(0.5), Some(1.0), None]).unwrap_err();
+        assert!(err.contains("color 3"), "{}", err);
+    }
+}
@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use palette::white_point::D65;
+use palette::{Lcha, Pixel, Srgba};
+
+use super::cvd::simulate;
+use super::gamut::Gamut;
+use crate::{output_path, print_colors, save_image_alpha, Config};
+
+/// Color harmonies, made by rotating the hue of a seed color in `Lch` while
+/// keeping its lightness and chroma.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Harmony {
+    Complementary,
+    SplitComplementary,
+    Analogous,
+    Triadic,
+    Tetradic,
+    Square,
+}
+
+impl Harmony {
+    /// Every harmony, in the order of the contact sheet.
+    pub const ALL: [Harmony; 6] = [
+        Harmony::Complementary,
+        Harmony::SplitComplementary,
+        Harmony::Analogous,
+        Harmony::Triadic,
+        Harmony::Tetradic,
+        Harmony::Square,
+    ];
+
+    /// The hue rotations of the seed in degrees. Analogous colors keep the
+    /// seed in the middle, so their gradient turns one way around the wheel.
+    pub fn angles(self) -> &'static [f32] {
+        match self {
+            Harmony::Complementary => &[0.0, 180.0],
+            Harmony::SplitComplementary => &[0.0, 150.0, 210.0],
+            Harmony::Analogous => &[-30.0, 0.0, 30.0],
+            Harmony::Triadic => &[0.0, 120.0, 240.0],
+            Harmony::Tetradic => &[0.0, 60.0, 180.0, 240.0],
+            Harmony::Square => &[0.0, 90.0, 180.0, 270.0],
+        }
+    }
+
+    /// The colors of the harmony for `seed`.
+    pub fn colors(self, seed: Lcha<D65>) -> Vec<Lcha<D65>> {
+        let hue = seed.hue.to_positive_degrees();
+        self.angles()
+            .iter()
+            .map(|&angle| {
+                let mut color = seed;
+                color.hue = (hue + angle).rem_euclid(360.0).into();
+                color
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Harmony {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Harmony::Complementary => "complementary",
+            Harmony::SplitComplementary => "split-complementary",
+            Harmony::Analogous => "analogous",
+            Harmony::Triadic => "triadic",
+            Harmony::Tetradic => "tetradic",
+            Harmony::Square => "square",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Harmony {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "complementary" => Ok(Harmony::Complementary),
+            "split-complementary" | "split" => Ok(Harmony::SplitComplementary),
+            "analogous" => Ok(Harmony::Analogous),
+            "triadic" => Ok(Harmony::Triadic),
+            "tetradic" | "rectangle" => Ok(Harmony::Tetradic),
+            "square" => Ok(Harmony::Square),
+            _ => Err(format!("Unsupported color harmony `{}`", s)),
+        }
+    }
+}
+
+/// Creates a contact sheet of every harmony of the first input color, one row
+/// of swatches per harmony in the order of `Harmony::ALL`. With `--print`,
+/// each row's colors are printed on their own line.
+pub fn harmony_sheet(config: Config) -> Result<(), Box<dyn Error>> {
+    let seed = config.grad_vec[0];
+    let gamut = Gamut::new(&config);
+    let rows: Vec<Vec<Srgba>> = Harmony::ALL
+        .iter()
+        .map(|h| {
+            h.colors(seed)
+                .into_iter()
+                .map(|c| gamut.to_srgb(c.into()))
+                .collect()
+        })
+        .collect();
+    gamut.report(config.gamut_report, "colors");
+
+    if config.print_grad {
+        rows.iter().for_each(|row| print_colors(row));
+    }
+    if config.no_file {
+        return Ok(());
+    }
+
+    let (sw, sh) = config.swatch_size;
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let mut imgbuf: image::RgbaImage =
+        image::ImageBuffer::new(sw * columns, sh * rows.len() as u32);
+
+    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
+        let row = &rows[(y / sh) as usize];
+        let pix: [u8; 4] = match row.get((x / sw) as usize) {
+            Some(&color) => simulate(config.simulate, color).into_format().into_raw(),
+            None => [0, 0, 0, 0],
+        };
+        *pixel = image::Rgba(pix);
+    }
+
+    let title = output_path(config.output_file.as_deref(), config.format)?;
+
+    save_image_alpha(&imgbuf, &title, config.format)
+}
@@ -1,59 +1,129 @@
 use std::error::Error;
-use std::path::PathBuf;
 
-use palette::{Gradient, LinSrgb, Pixel, Srgb};
+use palette::{Pixel, Srgba};
 
-use crate::{generate_filename, print_colors, save_image, Config};
+use super::analysis::report_steps;
+use super::contrast::report_contrast;
+use super::cvd::simulate;
+use super::dither::Quantizer;
+use super::gamut::Gamut;
+use super::svg::{linear_gradient_svg, linear_stepped_svg};
+use crate::{
+    output_path, print_colors, save_image, save_image16, save_image_alpha, save_image_alpha16,
+    save_palette, ColorGradient, Config, OutputFormat,
+};
 
 /// Creates an image of a linear, continuous gradient. The steps between each
 /// color should be indiscernible given a large enough image size.
 pub fn linear_gradient_continuous(config: Config) -> Result<(), Box<dyn Error>> {
-    let grad = Gradient::new(config.grad_vec);
+    let grad = ColorGradient::new(&config);
+    let gamut = Gamut::new(&config);
     let img_x = config.swatch_size.0;
     let img_y = config.swatch_size.1;
-    let mut imgbuf: image::RgbImage = image::ImageBuffer::new(img_x, img_y);
 
-    for x in 0..img_x {
-        let pix: [u8; 3] = Srgb::from_linear(LinSrgb::from(grad.get(x as f32 / img_x as f32)))
-            .into_format()
-            .into_raw();
-        for y in 0..img_y {
-            let pixel = imgbuf.get_pixel_mut(x, y);
-            *pixel = image::Rgb(pix);
+    if config.format == OutputFormat::Svg {
+        let title = output_path(config.output_file.as_deref(), config.format)?;
+        linear_gradient_svg(
+            &grad,
+            &gamut,
+            config.simulate,
+            config.swatch_size,
+            config.svg_tolerance,
+            &title,
+        )?;
+        gamut.report(config.gamut_report, "stops");
+        return Ok(());
+    }
+
+    let columns: Vec<Srgba> = (0..img_x)
+        .map(|x| gamut.to_srgb_n(grad.get(x as f32 / img_x as f32), img_y as usize))
+        .map(|c| simulate(config.simulate, c))
+        .collect();
+    gamut.report(config.gamut_report, "pixels");
+
+    let title = output_path(config.output_file.as_deref(), config.format)?;
+
+    // Alpha is only written when a stop is translucent.
+    let translucent = config.grad_vec.iter().any(|c| c.alpha < 1.0);
+
+    if config.depth == 16 {
+        if translucent {
+            let imgbuf = image::ImageBuffer::from_fn(img_x, img_y, |x, _| {
+                image::Rgba(columns[x as usize].into_format::<u16, u16>().into_raw())
+            });
+            return save_image_alpha16(&imgbuf, &title, config.format);
         }
+        let imgbuf = image::ImageBuffer::from_fn(img_x, img_y, |x, _| {
+            image::Rgb(columns[x as usize].color.into_format::<u16>().into_raw())
+        });
+        return save_image16(&imgbuf, &title, config.format);
     }
 
-    let mut title = PathBuf::from(generate_filename()?);
-    title.set_extension("png");
+    let mut quantizer = Quantizer::new(config.dither, img_x, config.seed);
+    if translucent {
+        let imgbuf: image::RgbaImage = image::ImageBuffer::from_fn(img_x, img_y, |x, y| {
+            image::Rgba(quantizer.quantize(x, y, columns[x as usize]))
+        });
+        return save_image_alpha(&imgbuf, &title, config.format);
+    }
 
-    save_image(&imgbuf, &title)
+    let mut imgbuf: image::RgbImage = image::ImageBuffer::new(img_x, img_y);
+    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
+        let pix = quantizer.quantize(x, y, columns[x as usize]);
+        *pixel = image::Rgb([pix[0], pix[1], pix[2]]);
+    }
+
+    save_image(&imgbuf, &title, config.format)
 }
 
 /// Creates an image of a linear, stepped gradient. The steps between each
 /// color are discrete and noticeable compared to a continuous gradient.
 pub fn linear_gradient_stepped(config: Config) -> Result<(), Box<dyn Error>> {
-    let grad1 = Gradient::new(config.grad_vec);
-    let grad2 = grad1.take(config.steps);
+    let grad1 = ColorGradient::new(&config);
+    let grad2 = grad1.take_spaced(config.steps, config.spacing);
+    config.spacing.report(&grad2);
+    let gamut = Gamut::new(&config);
 
     let mut grad_vec = Vec::with_capacity(config.steps);
     grad2
         .into_iter()
-        .for_each(|c| grad_vec.push(Srgb::from_linear(LinSrgb::from(c))));
+        .for_each(|c| grad_vec.push(gamut.to_srgb(c)));
+    gamut.report(config.gamut_report, "steps");
 
     if config.print_grad {
         print_colors(&grad_vec);
     }
+    report_contrast(&config, &grad_vec)?;
+    report_steps(&config, &grad_vec)?;
+    save_palette(&config, &grad_vec)?;
     if config.no_file {
         return Ok(());
     }
+    let grad_vec: Vec<Srgba> = grad_vec
+        .iter()
+        .map(|&c| simulate(config.simulate, c))
+        .collect();
+    if config.format == OutputFormat::Svg {
+        let title = output_path(config.output_file.as_deref(), config.format)?;
+        return linear_stepped_svg(&grad_vec, config.swatch_size, &title);
+    }
 
     let img_x = config.swatch_size.0;
     let img_y = config.swatch_size.1;
-    let mut imgbuf: image::RgbImage =
-        image::ImageBuffer::new(img_x * config.steps as u32, img_y as u32);
+    let title = output_path(config.output_file.as_deref(), config.format)?;
 
-    for s in 0..config.steps {
-        let pix: [u8; 3] = grad_vec[s].into_format().into_raw();
+    if grad_vec.iter().any(|c| c.alpha < 1.0) {
+        let imgbuf: image::RgbaImage =
+            image::ImageBuffer::from_fn(img_x * config.steps as u32, img_y, |x, _| {
+                image::Rgba(grad_vec[(x / img_x) as usize].into_format().into_raw())
+            });
+        return save_image_alpha(&imgbuf, &title, config.format);
+    }
+
+    let mut imgbuf: image::RgbImage = image::ImageBuffer::new(img_x * config.steps as u32, img_y);
+
+    for (s, color) in grad_vec.iter().enumerate() {
+        let pix: [u8; 3] = color.color.into_format().into_raw();
         for y in 0..img_y {
             for x in (s as u32 * img_x)..((s as u32 + 1) * img_x) {
                 let pixel = imgbuf.get_pixel_mut(x, y);
@@ -62,8 +132,5 @@ pub fn linear_gradient_stepped(config: Config) -> Result<(), Box<dyn Error>> {
         }
     }
 
-    let mut title = PathBuf::from(generate_filename()?);
-    title.set_extension("png");
-
-    save_image(&imgbuf, &title)
+    save_image(&imgbuf, &title, config.format)
 }
@@ -0,0 +1,290 @@
+use std::fmt;
+use std::str::FromStr;
+
+use palette::white_point::D65;
+use palette::{Component, Lcha, LinSrgba, Pixel, Srgba};
+
+use super::radial::midpoint_xy_dist;
+
+/// How much of their brightness dimmed pixels keep.
+const DIM_FACTOR: f32 = 0.25;
+
+/// A gamut mask shape in wheel coordinates. Points are given as an angle in
+/// degrees, measured like the gradient around the wheel, and a radius from
+/// 0.0 at the center to 1.0 at the edge.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MaskShape {
+    /// `circle(angle radius, size)`, where the size is a normalized radius.
+    Circle { center: [f32; 2], radius: f32 },
+    /// `polygon(angle radius, angle radius, ...)` with at least 3 points.
+    Polygon(Vec<[f32; 2]>),
+}
+
+impl MaskShape {
+    /// Converts the shape to unit disc coordinates, with y pointing down like
+    /// the image, for a wheel starting at `angle_offset` radians.
+    fn to_disc(&self, angle_offset: f32) -> DiscShape {
+        let point = |p: [f32; 2]| {
+            let angle = p[0].to_radians() - angle_offset;
+            [p[1] * angle.cos(), p[1] * angle.sin()]
+        };
+        match self {
+            MaskShape::Circle { center, radius } => DiscShape::Circle(point(*center), *radius),
+            MaskShape::Polygon(points) => {
+                DiscShape::Polygon(points.iter().map(|&p| point(p)).collect())
+            }
+        }
+    }
+}
+
+impl FromStr for MaskShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || {
+            format!(
+                "Could not parse mask shape `{}`, expected `circle(angle radius, size)` or `polygon(angle radius, ...)`",
+                s
+            )
+        };
+        let s = s.trim();
+        let open = s.find('(').ok_or_else(err)?;
+        let args = s[open + 1..].strip_suffix(')').ok_or_else(err)?;
+        let parts: Vec<&str> = args.split(',').map(str::trim).collect();
+
+        let point = |p: &str| -> Result<[f32; 2], String> {
+            let values = p
+                .split_whitespace()
+                .map(|v| v.parse::<f32>().map_err(|_| err()))
+                .collect::<Result<Vec<_>, _>>()?;
+            match values[..] {
+                [angle, radius] => Ok([angle, radius]),
+                _ => Err(err()),
+            }
+        };
+
+        match s[..open].trim().to_ascii_lowercase().as_str() {
+            "circle" if parts.len() == 2 => Ok(MaskShape::Circle {
+                center: point(parts[0])?,
+                radius: parts[1].parse::<f32>().map_err(|_| err())?,
+            }),
+            "polygon" if parts.len() >= 3 => Ok(MaskShape::Polygon(
+                parts.iter().map(|&p| point(p)).collect::<Result<_, _>>()?,
+            )),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// A mask shape in unit disc coordinates.
+enum DiscShape {
+    Circle([f32; 2], f32),
+    Polygon(Vec<[f32; 2]>),
+}
+
+impl DiscShape {
+    fn contains(&self, p: [f32; 2]) -> bool {
+        match self {
+            DiscShape::Circle(c, r) => (p[0] - c[0]).hypot(p[1] - c[1]) <= *r,
+            DiscShape::Polygon(points) => {
+                // Even-odd rule, counting edges crossed by a ray to the right.
+                let mut inside = false;
+                let mut j = points.len() - 1;
+                for i in 0..points.len() {
+                    let (a, b) = (points[i], points[j]);
+                    if (a[1] > p[1]) != (b[1] > p[1])
+                        && p[0] < (b[0] - a[0]) * (p[1] - a[1]) / (b[1] - a[1]) + a[0]
+                    {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+        }
+    }
+
+    /// The distance from `p` to the outline of the shape.
+    fn outline_dist(&self, p: [f32; 2]) -> f32 {
+        match self {
+            DiscShape::Circle(c, r) => ((p[0] - c[0]).hypot(p[1] - c[1]) - r).abs(),
+            DiscShape::Polygon(points) => {
+                let mut dist = f32::MAX;
+                let mut j = points.len() - 1;
+                for i in 0..points.len() {
+                    dist = dist.min(segment_dist(p, points[j], points[i]));
+                    j = i;
+                }
+                dist
+            }
+        }
+    }
+}
+
+fn segment_dist(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let len_squared = dx * dx + dy * dy;
+    let t = if len_squared > 0.0 {
+        (((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / len_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p[0] - a[0] - t * dx).hypot(p[1] - a[1] - t * dy)
+}
+
+/// What happens to pixels outside of a gamut mask.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaskStyle {
+    Dim,
+    Desaturate,
+    Transparent,
+}
+
+impl fmt::Display for MaskStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            MaskStyle::Dim => "dim",
+            MaskStyle::Desaturate => "desaturate",
+            MaskStyle::Transparent => "transparent",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for MaskStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dim" => Ok(MaskStyle::Dim),
+            "desaturate" => Ok(MaskStyle::Desaturate),
+            "transparent" => Ok(MaskStyle::Transparent),
+            _ => Err(format!("Unsupported mask style `{}`", s)),
+        }
+    }
+}
+
+/// A gamut mask drawn over a wheel, made of the union of its shapes.
+#[derive(Clone, Debug)]
+pub struct GamutMask {
+    pub shapes: Vec<MaskShape>,
+    pub style: MaskStyle,
+    pub outline: Srgba,
+    /// Width of the outline in pixels, no outline is drawn for 0.
+    pub stroke: f32,
+}
+
+impl GamutMask {
+    /// Whether the point at `angle` as a factor of a full turn and normalized
+    /// `radius` is inside the mask.
+    pub fn contains(&self, angle: f32, radius: f32, angle_offset: f32) -> bool {
+        let theta = angle * core::f32::consts::PI * 2.0 - angle_offset;
+        let p = [radius * theta.cos(), radius * theta.sin()];
+        self.shapes
+            .iter()
+            .any(|shape| shape.to_disc(angle_offset).contains(p))
+    }
+
+    /// Whether any point along the ray at `angle`, between the normalized
+    /// `radius_inner` and the edge, is inside the mask. Used for the
+    /// sectors of stepped radial gradients.
+    pub fn contains_ray(&self, angle: f32, radius_inner: f32, angle_offset: f32) -> bool {
+        let samples = 64;
+        (0..=samples).any(|i| {
+            let radius = radius_inner + (1.0 - radius_inner) * i as f32 / samples as f32;
+            self.contains(angle, radius, angle_offset)
+        })
+    }
+
+    /// Keeps the colors of a stepped radial gradient whose sectors reach into
+    /// the mask. Color `k` is centered on `k / colors.len()` of a turn, the
+    /// circle closing on the first color.
+    pub(crate) fn filter_radial(
+        &self,
+        colors: &[Srgba],
+        radius_inner: f32,
+        angle_offset: f32,
+    ) -> Vec<Srgba> {
+        let grad_len = colors.len().max(1) as f32;
+        colors
+            .iter()
+            .enumerate()
+            .filter(|&(k, _)| self.contains_ray(k as f32 / grad_len, radius_inner, angle_offset))
+            .map(|(_, &c)| c)
+            .collect()
+    }
+
+    /// Draws the mask over a wheel image of 8 or 16 bits per channel: pixels
+    /// outside of every shape are styled and the outline of the union is
+    /// stroked.
+    pub(crate) fn apply<T>(
+        &self,
+        imgbuf: &mut image::ImageBuffer<image::Rgba<T>, Vec<T>>,
+        angle_offset: f32,
+    ) where
+        T: image::Primitive + Component + 'static,
+    {
+        let (img_x, img_y) = imgbuf.dimensions();
+        let rad_outer = img_x as f32 * 0.5;
+        let shapes: Vec<DiscShape> = self
+            .shapes
+            .iter()
+            .map(|s| s.to_disc(angle_offset))
+            .collect();
+        let half_stroke = 0.5 * self.stroke / rad_outer;
+        let outline: [T; 4] = self.outline.into_format().into_raw();
+
+        for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
+            if pixel[3] == T::zero() {
+                continue;
+            }
+            let dist = midpoint_xy_dist(img_x, img_y, x, y);
+            let p = [dist[0] / rad_outer, dist[1] / rad_outer];
+            let inside = shapes.iter().position(|s| s.contains(p));
+
+            // Outlines inside another shape are hidden, leaving the outline
+            // of the union.
+            let on_outline = self.stroke > 0.0
+                && shapes.iter().enumerate().any(|(i, s)| {
+                    s.outline_dist(p) <= half_stroke
+                        && shapes
+                            .iter()
+                            .enumerate()
+                            .all(|(j, other)| i == j || !other.contains(p))
+                });
+            if on_outline {
+                *pixel = image::Rgba(outline);
+            } else if inside.is_none() {
+                *pixel = image::Rgba(self.style_pixel(pixel.0));
+            }
+        }
+    }
+
+    fn style_pixel<T: Component>(&self, pix: [T; 4]) -> [T; 4] {
+        match self.style {
+            MaskStyle::Dim => {
+                let color = Srgba::new(pix[0], pix[1], pix[2], pix[3])
+                    .into_format::<f32, f32>()
+                    .into_linear();
+                let dimmed = LinSrgba::new(
+                    color.red * DIM_FACTOR,
+                    color.green * DIM_FACTOR,
+                    color.blue * DIM_FACTOR,
+                    color.alpha,
+                );
+                Srgba::from_linear(dimmed).into_format().into_raw()
+            }
+            MaskStyle::Desaturate => {
+                let color = Srgba::new(pix[0], pix[1], pix[2], pix[3])
+                    .into_format::<f32, f32>()
+                    .into_linear();
+                let mut lch = Lcha::<D65>::from(color);
+                lch.chroma = 0.0;
+                Srgba::from_linear(LinSrgba::from(lch))
+                    .into_format()
+                    .into_raw()
+            }
+            MaskStyle::Transparent => [T::zero(); 4],
+        }
+    }
+}
@@ -1,39 +1,114 @@
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use palette::{Lch, LinSrgba, Srgb};
+use palette::white_point::D65;
+use palette::{Lcha, LinSrgba, Srgba};
 
+pub use analysis::AnalysisFormat;
+pub use color::{parse_colors, ColorError, ColorStop, Notation};
+pub use contrast::{ContrastFormat, ContrastTarget};
+pub use cvd::{simulation_sheet, Deficiency, Simulation};
+pub use dither::DitherMethod;
+pub use easing::Easing;
+pub use err::CliError;
+pub use extract::{extract_colors, ExtractMethod, ExtractSort};
+pub use format::OutputFormat;
+pub use gamut::GamutMethod;
+pub use gradient::{resolve_positions, wrap_stops, ColorGradient, HueMethod, StepSpacing};
+pub use harmony::{harmony_sheet, Harmony};
 pub use linear::{linear_gradient_continuous, linear_gradient_stepped};
+pub use mask::{GamutMask, MaskShape, MaskStyle};
 pub use radial::{
-    radial_gradient_continuous, radial_gradient_stepped, radial_gradient_with_overlay,
+    concentric_gradient_continuous, concentric_gradient_stepped, radial_gradient_continuous,
+    radial_gradient_stepped, radial_gradient_with_overlay,
 };
+pub use slice::{hcl_slice, SliceOutside};
+pub use space::Space;
+pub use swatch::{palette_only, read_palette, PaletteFormat};
+pub use wheel::{wheel_gradient_continuous, wheel_gradient_stepped, WheelChannel};
 
+mod analysis;
+mod color;
+mod contrast;
+mod cvd;
+mod diff;
+mod dither;
+mod easing;
 mod err;
+mod extract;
+mod format;
+mod gamut;
+mod gradient;
+mod harmony;
 mod linear;
+mod mask;
 mod radial;
+mod rng;
+mod slice;
+mod space;
+mod svg;
+mod swatch;
+mod wheel;
 
 pub(crate) enum Work {
+    ConGradCont,
+    ConGradStep,
+    HarmonySheet,
+    HclSlice,
     LinGradCont,
     LinGradStep,
+    PaletteOnly,
     RadGradCont,
     RadGradContOverlay,
     RadGradStep,
+    SimulationSheet,
+    WheelCont,
+    WheelStep,
 }
 
 pub struct Config {
+    pub analyze: Option<AnalysisFormat>,
+    pub analyze_file: Option<PathBuf>,
     pub angle_offset: f32,
-    pub grad_vec: Vec<Lch>,
+    pub contrast: Option<ContrastFormat>,
+    pub contrast_file: Option<PathBuf>,
+    pub contrast_target: ContrastTarget,
+    pub depth: u8,
+    pub dither: DitherMethod,
+    pub easing: Vec<Easing>,
+    pub grad_vec: Vec<Lcha<D65>>,
+    pub hue: Vec<HueMethod>,
     pub linear: bool,
+    pub mask: Option<GamutMask>,
     pub radius_inner: f32,
+    pub format: OutputFormat,
+    pub gamut: GamutMethod,
+    pub gamut_report: bool,
     pub no_file: bool,
     pub overlay: LinSrgba,
     pub overlay_factor: f32,
     pub output_file: Option<PathBuf>,
+    pub palette_columns: Option<usize>,
+    pub palette_file: Option<PathBuf>,
+    pub palette_name: Option<String>,
+    pub positions: Vec<f32>,
     pub print_grad: bool,
+    pub rings: usize,
+    pub seed: u64,
+    pub severity: f32,
+    pub simulate: Option<Simulation>,
     pub size: u32,
+    pub slice_chroma: f32,
+    pub slice_lightness: f32,
+    pub slice_outside: SliceOutside,
+    pub space: Space,
+    pub spacing: StepSpacing,
     pub steps: usize,
+    pub svg_tolerance: f32,
     pub swatch_size: (u32, u32),
+    pub wheel_channel: WheelChannel,
+    pub wheel_range: (f32, f32),
 }
 
 /// Generate a filename with a Unix timestamp.
@@ -44,40 +119,196 @@ pub(crate) fn generate_filename() -> Result<String, Box<dyn Error>> {
     Ok(secs.to_string() + &millis)
 }
 
-/// Displays the colors in a stepped gradient.
-pub(crate) fn print_colors(colors: &Vec<Srgb>) {
+/// Displays the colors in a stepped gradient. The alpha is appended to the
+/// hex value for colors which aren't fully opaque.
+pub(crate) fn print_colors(colors: &[Srgba]) {
+    let hex = |c: &Srgba| {
+        let c = c.into_format::<u8, u8>();
+        if c.alpha == u8::MAX {
+            format!("{:x}", c.color)
+        } else {
+            format!("{:x}{:02x}", c.color, c.alpha)
+        }
+    };
     if let Some((last, elements)) = colors.split_last() {
         for c in elements {
-            print!("{:x},", c.into_format::<u8>());
+            print!("{},", hex(c));
         }
-        print!("{:x}\n", last.into_format::<u8>());
+        println!("{}", hex(last));
     }
 }
 
-/// Saves `RgbImage` provided a buffer and file path.
-pub(crate) fn save_image(imgbuf: &image::RgbImage, title: &PathBuf) -> Result<(), Box<dyn Error>> {
-    match imgbuf.save(&title) {
-        Ok(_) => {}
-        Err(err) => {
-            eprintln!("Error: {}.", err);
-            std::fs::remove_file(&title)?;
+/// Saves the colors of a stepped gradient to `config.palette_file`, if one
+/// was given, in the format of its extension. A GIMP palette is named after
+/// the file unless `--palette-name` is set, and has a single row unless
+/// `--palette-columns` is set. An ASE file only has a group when
+/// `--palette-name` is set.
+pub(crate) fn save_palette(config: &Config, colors: &[Srgba]) -> Result<(), Box<dyn Error>> {
+    let mut path = match &config.palette_file {
+        Some(path) => path.clone(),
+        None => return Ok(()),
+    };
+    let format = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext.parse::<PaletteFormat>()?,
+        None => {
+            path.set_extension(PaletteFormat::Gpl.extension());
+            PaletteFormat::Gpl
+        }
+    };
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
         }
     }
 
-    Ok(())
+    match format {
+        PaletteFormat::Ase => swatch::write_ase(colors, config.palette_name.as_deref(), &path),
+        PaletteFormat::Aco => swatch::write_aco(colors, &path),
+        PaletteFormat::Gpl => {
+            let name = match &config.palette_name {
+                Some(name) => name.clone(),
+                None => path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            };
+            let columns = config.palette_columns.unwrap_or(colors.len());
+            swatch::write_gpl(colors, &name, columns, &path)
+        }
+    }
+}
+
+/// Writes a text report to `path`, or to stdout if there's none.
+pub(crate) fn write_report(text: &str, path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    match path {
+        Some(path) => std::fs::write(path, text)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e).into()),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
 }
 
-/// Saves `RgbaImage` provided a buffer and file path.
+/// Returns the path of the image to write. Uses the output file if one was
+/// given, otherwise a timestamped filename in the working directory. Missing
+/// parent directories are created.
+pub(crate) fn output_path(
+    output_file: Option<&Path>,
+    format: OutputFormat,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let mut title = match output_file {
+        Some(path) => path.to_path_buf(),
+        None => PathBuf::from(generate_filename()?),
+    };
+    if title.extension().is_none() {
+        title.set_extension(format.extension());
+    }
+
+    if let Some(parent) = title.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+
+    Ok(title)
+}
+
+/// Saves `RgbImage` provided a buffer, file path and format.
+pub(crate) fn save_image(
+    imgbuf: &image::RgbImage,
+    title: &Path,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let (width, height) = imgbuf.dimensions();
+    save_buffer(imgbuf, width, height, image::ColorType::Rgb8, title, format)
+}
+
+/// Saves `RgbaImage` provided a buffer, file path and format.
 pub(crate) fn save_image_alpha(
     imgbuf: &image::RgbaImage,
-    title: &PathBuf,
+    title: &Path,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let (width, height) = imgbuf.dimensions();
+    save_buffer(
+        imgbuf,
+        width,
+        height,
+        image::ColorType::Rgba8,
+        title,
+        format,
+    )
+}
+
+/// Saves a 16-bit `Rgb` buffer provided a file path and format.
+pub(crate) fn save_image16(
+    imgbuf: &image::ImageBuffer<image::Rgb<u16>, Vec<u16>>,
+    title: &Path,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let (width, height) = imgbuf.dimensions();
+    save_buffer16(
+        imgbuf,
+        width,
+        height,
+        image::ColorType::Rgb16,
+        title,
+        format,
+    )
+}
+
+/// Saves a 16-bit `Rgba` buffer provided a file path and format.
+pub(crate) fn save_image_alpha16(
+    imgbuf: &image::ImageBuffer<image::Rgba<u16>, Vec<u16>>,
+    title: &Path,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let (width, height) = imgbuf.dimensions();
+    save_buffer16(
+        imgbuf,
+        width,
+        height,
+        image::ColorType::Rgba16,
+        title,
+        format,
+    )
+}
+
+/// Writes the raw buffer, removing any partially written file on failure.
+fn save_buffer(
+    buf: &[u8],
+    width: u32,
+    height: u32,
+    color: image::ColorType,
+    title: &Path,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    if let Err(err) = format::write_buffer(buf, width, height, color, title, format) {
+        if title.exists() {
+            std::fs::remove_file(title)?;
+        }
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// Writes the raw 16-bit buffer, removing any partially written file on
+/// failure.
+fn save_buffer16(
+    buf: &[u16],
+    width: u32,
+    height: u32,
+    color: image::ColorType,
+    title: &Path,
+    format: OutputFormat,
 ) -> Result<(), Box<dyn Error>> {
-    match imgbuf.save(&title) {
-        Ok(_) => {}
-        Err(err) => {
-            eprintln!("Error: {}.", err);
-            std::fs::remove_file(&title)?;
+    if let Err(err) = format::write_buffer16(buf, width, height, color, title, format) {
+        if title.exists() {
+            std::fs::remove_file(title)?;
         }
+        return Err(err);
     }
 
     Ok(())
@@ -1,61 +1,96 @@
 use std::error::Error;
-use std::path::PathBuf;
 
-use palette::{Blend, Gradient, LinSrgb, LinSrgba, Pixel, Srgb, Srgba};
+use palette::{Blend, Pixel, Srgba};
 
-use crate::{generate_filename, print_colors, save_image_alpha, Config};
+use super::analysis::report_steps;
+use super::contrast::report_contrast;
+use super::cvd::simulate;
+use super::dither::Quantizer;
+use super::gamut::Gamut;
+use super::svg::radial_stepped_svg;
+use crate::{
+    output_path, print_colors, save_image_alpha, save_image_alpha16, save_palette, ColorGradient,
+    Config, OutputFormat,
+};
 
 /// Finds the midpoint between a point in the image and the center of the image
 /// with width `size_x` and height `size_y`.
-fn midpoint_xy_dist(size_x: u32, size_y: u32, x2: u32, y2: u32) -> [f32; 2] {
+pub(crate) fn midpoint_xy_dist(size_x: u32, size_y: u32, x2: u32, y2: u32) -> [f32; 2] {
     let mut result: [f32; 2] = [0.0, 0.0];
     result[0] = (x2 as f32) - ((size_x as f32 / 2.0) - 1.0);
     result[1] = (y2 as f32) - ((size_y as f32 / 2.0) - 1.0);
     result
 }
 
+/// Renders the colors of `color_at` for each pixel, `None` being transparent,
+/// with the bit depth and color vision simulation of `config`. Applies the
+/// gamut mask and saves the image.
+fn save_continuous<F>(config: &Config, gamut: &Gamut, color_at: F) -> Result<(), Box<dyn Error>>
+where
+    F: Fn(u32, u32) -> Option<Srgba>,
+{
+    let img_x = config.size;
+    let img_y = config.size;
+    let title = output_path(config.output_file.as_deref(), config.format)?;
+    let color_at = |x, y| color_at(x, y).map(|c| simulate(config.simulate, c));
+
+    if config.depth == 16 {
+        let mut imgbuf = image::ImageBuffer::from_fn(img_x, img_y, |x, y| {
+            image::Rgba(match color_at(x, y) {
+                Some(color) => color.into_format::<u16, u16>().into_raw(),
+                None => [0, 0, 0, 0],
+            })
+        });
+        gamut.report(config.gamut_report, "pixels");
+        if let Some(mask) = &config.mask {
+            mask.apply(&mut imgbuf, config.angle_offset);
+        }
+        return save_image_alpha16(&imgbuf, &title, config.format);
+    }
+
+    let mut quantizer = Quantizer::new(config.dither, img_x, config.seed);
+    let mut imgbuf = image::ImageBuffer::from_fn(img_x, img_y, |x, y| {
+        image::Rgba(match color_at(x, y) {
+            Some(color) => quantizer.quantize(x, y, color),
+            None => [0, 0, 0, 0],
+        })
+    });
+    gamut.report(config.gamut_report, "pixels");
+    if let Some(mask) = &config.mask {
+        mask.apply(&mut imgbuf, config.angle_offset);
+    }
+
+    save_image_alpha(&imgbuf, &title, config.format)
+}
+
 /// Creates an image of a circular, continuous gradient. The steps between each
 /// color will be indiscernible given a large enough image size.
 pub fn radial_gradient_continuous(config: Config) -> Result<(), Box<dyn Error>> {
-    let grad = Gradient::new(config.grad_vec);
+    let grad = ColorGradient::new(&config);
+    let gamut = Gamut::new(&config);
     let img_x = config.size;
     let img_y = config.size;
-    let mut imgbuf: image::RgbaImage = image::ImageBuffer::new(img_x, img_y);
     let rad_squared = (config.size as f32 * 0.5).powi(2);
     let rad_inner = (config.size as f32 * config.radius_inner).powi(2);
     let tau = core::f32::consts::PI * 2.0;
     let angle_offset = config.angle_offset;
 
-    let mut pix: [u8; 4];
-    let mut dist: [f32; 2];
-    let mut dist_squared;
-    let mut arctan_res;
-
-    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
-        dist = midpoint_xy_dist(img_x, img_y, x, y);
-        dist_squared = dist[0] * dist[0] + dist[1] * dist[1];
-        if dist_squared >= rad_inner && dist_squared <= rad_squared {
-            arctan_res = dist[1].atan2(dist[0]);
-            if arctan_res.is_sign_negative() {
-                arctan_res += tau;
-            }
-            arctan_res += angle_offset;
-            if arctan_res > tau {
-                arctan_res = arctan_res.rem_euclid(tau);
-            }
-            pix = Srgba::from_linear(LinSrgba::from(grad.get(arctan_res / tau)))
-                .into_format()
-                .into_raw();
-        } else {
-            pix = [0, 0, 0, 0];
+    save_continuous(&config, &gamut, |x, y| {
+        let dist = midpoint_xy_dist(img_x, img_y, x, y);
+        let dist_squared = dist[0] * dist[0] + dist[1] * dist[1];
+        if dist_squared < rad_inner || dist_squared > rad_squared {
+            return None;
         }
-        *pixel = image::Rgba(pix);
-    }
-
-    let mut title = PathBuf::from(generate_filename()?);
-    title.set_extension("png");
-
-    save_image_alpha(&imgbuf, &title)
+        let mut arctan_res = dist[1].atan2(dist[0]);
+        if arctan_res.is_sign_negative() {
+            arctan_res += tau;
+        }
+        arctan_res += angle_offset;
+        if arctan_res > tau {
+            arctan_res = arctan_res.rem_euclid(tau);
+        }
+        Some(gamut.to_srgb(grad.get(arctan_res / tau)))
+    })
 }
 
 /// Creates an image of a circular, stepped gradient. The steps between each
@@ -65,20 +100,49 @@ pub fn radial_gradient_stepped(config: Config) -> Result<(), Box<dyn Error>> {
     // for radial gradients. Otherwise, we're left with `steps - 1` colors.
     let steps = config.steps + 1;
 
-    let grad1 = Gradient::new(config.grad_vec);
-    let grad2 = grad1.take(steps);
+    let grad1 = ColorGradient::new(&config);
+    let grad2 = grad1.take_spaced(steps, config.spacing);
+    config.spacing.report(&grad2);
+
+    let gamut = Gamut::new(&config);
 
+    // The last step repeats the first to close the circle, so it's only
+    // drawn and not printed or saved.
     let mut grad_vec = Vec::with_capacity(steps);
-    grad2
-        .into_iter()
-        .for_each(|c| grad_vec.push(Srgb::from_linear(LinSrgb::from(c))));
+    grad2[..config.steps]
+        .iter()
+        .for_each(|&c| grad_vec.push(gamut.to_srgb(c)));
+    gamut.report(config.gamut_report, "steps");
 
+    // Only the colors inside a gamut mask are printed and saved.
+    let palette = match &config.mask {
+        Some(mask) => mask.filter_radial(&grad_vec, config.radius_inner * 2.0, config.angle_offset),
+        None => grad_vec.clone(),
+    };
     if config.print_grad {
-        print_colors(&grad_vec);
+        print_colors(&palette);
     }
+    report_contrast(&config, &palette)?;
+    report_steps(&config, &palette)?;
+    save_palette(&config, &palette)?;
     if config.no_file {
         return Ok(());
     }
+    let mut grad_vec: Vec<Srgba> = grad_vec
+        .iter()
+        .map(|&c| simulate(config.simulate, c))
+        .collect();
+    grad_vec.push(grad_vec[0]);
+    if config.format == OutputFormat::Svg {
+        let title = output_path(config.output_file.as_deref(), config.format)?;
+        return radial_stepped_svg(
+            &grad_vec,
+            config.size,
+            config.radius_inner,
+            config.angle_offset,
+            &title,
+        );
+    }
 
     let img_x = config.size;
     let img_y = config.size;
@@ -106,7 +170,7 @@ pub fn radial_gradient_stepped(config: Config) -> Result<(), Box<dyn Error>> {
             if arctan_res > tau {
                 arctan_res = arctan_res.rem_euclid(tau);
             }
-            pix = Srgba::from(grad_vec[((arctan_res / tau) * grad_len).round() as usize])
+            pix = grad_vec[((arctan_res / tau) * grad_len).round() as usize]
                 .into_format()
                 .into_raw();
         } else {
@@ -115,55 +179,136 @@ pub fn radial_gradient_stepped(config: Config) -> Result<(), Box<dyn Error>> {
         *pixel = image::Rgba(pix);
     }
 
-    let mut title = PathBuf::from(generate_filename()?);
-    title.set_extension("png");
+    if let Some(mask) = &config.mask {
+        mask.apply(&mut imgbuf, config.angle_offset);
+    }
+    let title = output_path(config.output_file.as_deref(), config.format)?;
 
-    save_image_alpha(&imgbuf, &title)
+    save_image_alpha(&imgbuf, &title, config.format)
 }
 
 /// Creates an image of a circular, continuous gradient with a color overlay.
 /// The color is overlayed using the blending mode `atop`. A blending factor
 /// can be used to adjust the radius of the overlay blending.
 pub fn radial_gradient_with_overlay(config: Config) -> Result<(), Box<dyn Error>> {
-    let grad = Gradient::new(config.grad_vec);
+    let grad = ColorGradient::new(&config);
+    let gamut = Gamut::new(&config);
     let angle_offset = config.angle_offset;
     let factor = config.overlay_factor;
     let img_x = config.size;
     let img_y = config.size;
-    let mut imgbuf: image::RgbaImage = image::ImageBuffer::new(img_x, img_y);
     let rad_squared = (config.size as f32 * 0.5).powi(2);
     let two_pi = core::f32::consts::PI * 2.0;
 
+    save_continuous(&config, &gamut, |x, y| {
+        let dist = midpoint_xy_dist(img_x, img_y, x, y);
+        let dist_squared = dist[0] * dist[0] + dist[1] * dist[1];
+        if dist_squared > rad_squared {
+            return None;
+        }
+        let mut arctan_res = dist[1].atan2(dist[0]);
+        if arctan_res.is_sign_negative() {
+            arctan_res += two_pi;
+        }
+        arctan_res += angle_offset;
+        if arctan_res > two_pi {
+            arctan_res = arctan_res.rem_euclid(two_pi);
+        }
+        let mut overlay = config.overlay;
+        overlay.alpha *= (1.0 - (dist_squared / rad_squared)) * factor;
+        Some(gamut.to_srgb(overlay.atop(grad.get(arctan_res / two_pi))))
+    })
+}
+
+/// Creates an image of a concentric, continuous gradient. Colors change with
+/// the distance from the center, running from the inner radius to the edge.
+pub fn concentric_gradient_continuous(config: Config) -> Result<(), Box<dyn Error>> {
+    let grad = ColorGradient::new(&config);
+    let gamut = Gamut::new(&config);
+    let img_x = config.size;
+    let img_y = config.size;
+    let mut imgbuf: image::RgbaImage = image::ImageBuffer::new(img_x, img_y);
+    let mut quantizer = Quantizer::new(config.dither, img_x, config.seed);
+    let rad_outer = config.size as f32 * 0.5;
+    let rad_inner = config.size as f32 * config.radius_inner;
+
     let mut pix: [u8; 4];
     let mut dist: [f32; 2];
-    let mut arctan_res;
-    let mut temp;
-    let mut dist_squared;
-    let mut overlay = config.overlay;
+    let mut radius;
+
     for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
         dist = midpoint_xy_dist(img_x, img_y, x, y);
-        dist_squared = dist[0] * dist[0] + dist[1] * dist[1];
-        if (dist_squared) <= rad_squared {
-            arctan_res = dist[1].atan2(dist[0]);
-            if arctan_res.is_sign_negative() {
-                arctan_res += two_pi;
-            }
-            arctan_res += angle_offset;
-            if arctan_res > two_pi {
-                arctan_res = arctan_res.rem_euclid(two_pi);
-            }
-            temp = LinSrgba::from(grad.get(arctan_res / two_pi));
-            overlay.alpha = (1.0 - (dist_squared / rad_squared)) * factor;
-            temp = overlay.atop(temp);
-            pix = Srgba::from_linear(temp).into_format().into_raw();
+        radius = (dist[0] * dist[0] + dist[1] * dist[1]).sqrt();
+        if radius >= rad_inner && radius <= rad_outer {
+            let color = gamut.to_srgb(grad.get((radius - rad_inner) / (rad_outer - rad_inner)));
+            pix = quantizer.quantize(x, y, simulate(config.simulate, color));
+        } else {
+            pix = [0, 0, 0, 0];
+        }
+        *pixel = image::Rgba(pix);
+    }
+    gamut.report(config.gamut_report, "pixels");
+
+    let title = output_path(config.output_file.as_deref(), config.format)?;
+
+    save_image_alpha(&imgbuf, &title, config.format)
+}
+
+/// Creates an image of concentric rings, one ring of equal width for each
+/// color step.
+pub fn concentric_gradient_stepped(config: Config) -> Result<(), Box<dyn Error>> {
+    let grad1 = ColorGradient::new(&config);
+    let grad2 = grad1.take_spaced(config.steps, config.spacing);
+    config.spacing.report(&grad2);
+
+    let gamut = Gamut::new(&config);
+
+    let mut grad_vec = Vec::with_capacity(config.steps);
+    grad2
+        .into_iter()
+        .for_each(|c| grad_vec.push(gamut.to_srgb(c)));
+    gamut.report(config.gamut_report, "steps");
+
+    if config.print_grad {
+        print_colors(&grad_vec);
+    }
+    report_contrast(&config, &grad_vec)?;
+    report_steps(&config, &grad_vec)?;
+    save_palette(&config, &grad_vec)?;
+    if config.no_file {
+        return Ok(());
+    }
+    let grad_vec: Vec<Srgba> = grad_vec
+        .iter()
+        .map(|&c| simulate(config.simulate, c))
+        .collect();
+
+    let img_x = config.size;
+    let img_y = config.size;
+    let mut imgbuf: image::RgbaImage = image::ImageBuffer::new(img_x, img_y);
+    let rad_outer = config.size as f32 * 0.5;
+    let rad_inner = config.size as f32 * config.radius_inner;
+    let rings = grad_vec.len();
+
+    let mut pix: [u8; 4];
+    let mut dist: [f32; 2];
+    let mut radius;
+    let mut ring;
+
+    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
+        dist = midpoint_xy_dist(img_x, img_y, x, y);
+        radius = (dist[0] * dist[0] + dist[1] * dist[1]).sqrt();
+        if radius >= rad_inner && radius <= rad_outer {
+            // Pixels right on the edge belong to the outermost ring.
+            ring = ((radius - rad_inner) / (rad_outer - rad_inner) * rings as f32) as usize;
+            pix = grad_vec[ring.min(rings - 1)].into_format().into_raw();
         } else {
             pix = [0, 0, 0, 0];
         }
         *pixel = image::Rgba(pix);
     }
 
-    let mut title = PathBuf::from(generate_filename()?);
-    title.set_extension("png");
+    let title = output_path(config.output_file.as_deref(), config.format)?;
 
-    save_image_alpha(&imgbuf, &title)
+    save_image_alpha(&imgbuf, &title, config.format)
 }
@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use palette::white_point::D65;
+use palette::{Lch, LinSrgb, LinSrgba, Pixel};
+
+use super::cvd::simulate;
+use super::gamut::{in_gamut, Gamut};
+use super::radial::midpoint_xy_dist;
+use super::wheel::angle_factor;
+use crate::{output_path, save_image_alpha, Config};
+
+/// Width in pixels of the stripes marking colors outside of sRGB.
+const STRIPE_WIDTH: u32 = 6;
+
+/// How pixels of a slice outside of the sRGB gamut are drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SliceOutside {
+    /// Left transparent, leaving only the reachable colors.
+    Transparent,
+    /// Mapped into gamut and striped with grey.
+    Mark,
+}
+
+impl fmt::Display for SliceOutside {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SliceOutside::Transparent => write!(f, "transparent"),
+            SliceOutside::Mark => write!(f, "mark"),
+        }
+    }
+}
+
+impl FromStr for SliceOutside {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "transparent" => Ok(SliceOutside::Transparent),
+            "mark" => Ok(SliceOutside::Mark),
+            _ => Err(format!("Unsupported slice style `{}`", s)),
+        }
+    }
+}
+
+/// Creates an image of the `Lch` color space at a fixed lightness. The angle
+/// sets the hue and the radius sets the chroma, from 0 at the center to
+/// `slice_chroma` at the edge, showing which colors are reachable in sRGB.
+pub fn hcl_slice(config: Config) -> Result<(), Box<dyn Error>> {
+    let gamut = Gamut::new(&config);
+    let img_x = config.size;
+    let img_y = config.size;
+    let mut imgbuf: image::RgbaImage = image::ImageBuffer::new(img_x, img_y);
+    let rad_outer = config.size as f32 * 0.5;
+
+    let mut pix: [u8; 4];
+    let mut dist: [f32; 2];
+    let mut radius;
+
+    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
+        dist = midpoint_xy_dist(img_x, img_y, x, y);
+        radius = (dist[0] * dist[0] + dist[1] * dist[1]).sqrt();
+        if radius <= rad_outer {
+            let hue = angle_factor(dist, config.angle_offset) * 360.0;
+            let chroma = config.slice_chroma * radius / rad_outer;
+            let color = LinSrgb::from(Lch::<D65>::new(config.slice_lightness, chroma, hue));
+            // Mapped colors are counted for the gamut report even when
+            // they're left transparent.
+            let mapped = simulate(config.simulate, gamut.to_srgb(LinSrgba::from(color)));
+            pix = match config.slice_outside {
+                _ if in_gamut(color) => mapped.into_format().into_raw(),
+                SliceOutside::Transparent => [0, 0, 0, 0],
+                SliceOutside::Mark if (x + y) % (2 * STRIPE_WIDTH) < STRIPE_WIDTH => {
+                    [128, 128, 128, 255]
+                }
+                SliceOutside::Mark => mapped.into_format().into_raw(),
+            };
+        } else {
+            pix = [0, 0, 0, 0];
+        }
+        *pixel = image::Rgba(pix);
+    }
+    gamut.report(config.gamut_report, "pixels");
+
+    if let Some(mask) = &config.mask {
+        mask.apply(&mut imgbuf, config.angle_offset);
+    }
+    let title = output_path(config.output_file.as_deref(), config.format)?;
+
+    save_image_alpha(&imgbuf, &title, config.format)
+}
@@ -0,0 +1,392 @@
+use std::fmt;
+use std::str::FromStr;
+
+use palette::white_point::D65;
+use palette::{Alpha, Hsla, Hsva, Laba, Lcha, LinSrgb, LinSrgba, Mix, Srgb};
+
+/// Color spaces which gradients can be interpolated in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Space {
+    Hsl,
+    Hsv,
+    Lab,
+    Lch,
+    LinearRgb,
+    Oklab,
+    Oklch,
+    Srgb,
+}
+
+impl fmt::Display for Space {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Space::Hsl => "hsl",
+            Space::Hsv => "hsv",
+            Space::Lab => "lab",
+            Space::Lch => "lch",
+            Space::LinearRgb => "linear",
+            Space::Oklab => "oklab",
+            Space::Oklch => "oklch",
+            Space::Srgb => "srgb",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Space {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hsl" => Ok(Space::Hsl),
+            "hsv" => Ok(Space::Hsv),
+            "lab" => Ok(Space::Lab),
+            "lch" | "hcl" => Ok(Space::Lch),
+            "linear" | "linear-rgb" => Ok(Space::LinearRgb),
+            "oklab" => Ok(Space::Oklab),
+            "oklch" => Ok(Space::Oklch),
+            "srgb" | "rgb" => Ok(Space::Srgb),
+            _ => Err(format!("Unsupported color space `{}`", s)),
+        }
+    }
+}
+
+/// Colors which gradients can be interpolated in. Input colors are converted
+/// from `Lcha` and interpolated colors are converted back to linear sRGB for
+/// rendering.
+pub trait SpaceColor: Mix<Scalar = f32> + Clone {
+    fn from_lcha(color: Lcha<D65>) -> Self;
+    fn into_linear(self) -> LinSrgba;
+
+    /// The hue in degrees, for cylindrical spaces.
+    fn hue(&self) -> Option<f32> {
+        None
+    }
+
+    /// Sets the hue in degrees, for cylindrical spaces.
+    fn set_hue(&mut self, _hue: f32) {}
+
+    /// The alpha, from 0.0 for transparent to 1.0 for opaque.
+    fn alpha(&self) -> f32;
+    fn set_alpha(&mut self, alpha: f32);
+
+    /// Mixes with `other` by `factor` in premultiplied alpha, like CSS Color 4.
+    /// Premultiplying the components and dividing by the mixed alpha is the
+    /// same as mixing the colors by a factor weighted by their alphas. Alpha
+    /// and hue are mixed by `factor` itself, except that a fully transparent
+    /// color takes the hue of the other, as CSS does for a missing hue.
+    fn mix_premultiplied(&self, other: &Self, factor: f32) -> Self {
+        let (a0, a1) = (self.alpha(), other.alpha());
+        let alpha = a0 + factor * (a1 - a0);
+        if a0 == a1 || alpha <= 0.0 {
+            return self.mix(other, factor);
+        }
+
+        let mut color = self.mix(other, factor * a1 / alpha);
+        let hue = if a0 == 0.0 {
+            other.hue()
+        } else if a1 == 0.0 {
+            self.hue()
+        } else {
+            self.mix(other, factor).hue()
+        };
+        if let Some(hue) = hue {
+            color.set_hue(hue);
+        }
+        color.set_alpha(alpha);
+        color
+    }
+}
+
+impl SpaceColor for Lcha<D65> {
+    fn from_lcha(color: Lcha<D65>) -> Self {
+        color
+    }
+    fn into_linear(self) -> LinSrgba {
+        LinSrgba::from(self)
+    }
+    fn hue(&self) -> Option<f32> {
+        Some(self.hue.to_positive_degrees())
+    }
+    fn set_hue(&mut self, hue: f32) {
+        self.hue = hue.into();
+    }
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+    fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+}
+
+impl SpaceColor for Laba<D65> {
+    fn from_lcha(color: Lcha<D65>) -> Self {
+        Laba::from(color)
+    }
+    fn into_linear(self) -> LinSrgba {
+        LinSrgba::from(self)
+    }
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+    fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+}
+
+impl SpaceColor for LinSrgba {
+    fn from_lcha(color: Lcha<D65>) -> Self {
+        LinSrgba::from(color)
+    }
+    fn into_linear(self) -> LinSrgba {
+        self
+    }
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+    fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+}
+
+impl SpaceColor for Hsva {
+    fn from_lcha(color: Lcha<D65>) -> Self {
+        Hsva::from(color)
+    }
+    fn into_linear(self) -> LinSrgba {
+        LinSrgba::from(self)
+    }
+    fn hue(&self) -> Option<f32> {
+        Some(self.hue.to_positive_degrees())
+    }
+    fn set_hue(&mut self, hue: f32) {
+        self.hue = hue.into();
+    }
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+    fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+}
+
+impl SpaceColor for Hsla {
+    fn from_lcha(color: Lcha<D65>) -> Self {
+        Hsla::from(color)
+    }
+    fn into_linear(self) -> LinSrgba {
+        LinSrgba::from(self)
+    }
+    fn hue(&self) -> Option<f32> {
+        Some(self.hue.to_positive_degrees())
+    }
+    fn set_hue(&mut self, hue: f32) {
+        self.hue = hue.into();
+    }
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+    fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+}
+
+impl SpaceColor for Alpha<GammaRgb, f32> {
+    fn from_lcha(color: Lcha<D65>) -> Self {
+        let rgba = LinSrgba::from(color);
+        let srgb = Srgb::from_linear(rgba.color);
+        Alpha {
+            color: GammaRgb {
+                red: srgb.red,
+                green: srgb.green,
+                blue: srgb.blue,
+            },
+            alpha: rgba.alpha,
+        }
+    }
+    fn into_linear(self) -> LinSrgba {
+        let c = self.color;
+        let linear = Srgb::new(c.red, c.green, c.blue).into_linear();
+        Alpha {
+            color: linear,
+            alpha: self.alpha,
+        }
+    }
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+    fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+}
+
+impl SpaceColor for Alpha<Oklab, f32> {
+    fn from_lcha(color: Lcha<D65>) -> Self {
+        let rgba = LinSrgba::from(color);
+        Alpha {
+            color: Oklab::from_linear(rgba.color),
+            alpha: rgba.alpha,
+        }
+    }
+    fn into_linear(self) -> LinSrgba {
+        Alpha {
+            color: self.color.into_linear(),
+            alpha: self.alpha,
+        }
+    }
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+    fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+}
+
+impl SpaceColor for Alpha<Oklch, f32> {
+    fn from_lcha(color: Lcha<D65>) -> Self {
+        let rgba = LinSrgba::from(color);
+        Alpha {
+            color: Oklch::from(Oklab::from_linear(rgba.color)),
+            alpha: rgba.alpha,
+        }
+    }
+    fn into_linear(self) -> LinSrgba {
+        Alpha {
+            color: Oklab::from(self.color).into_linear(),
+            alpha: self.alpha,
+        }
+    }
+    fn hue(&self) -> Option<f32> {
+        Some(self.color.hue)
+    }
+    fn set_hue(&mut self, hue: f32) {
+        self.color.hue = hue.rem_euclid(360.0);
+    }
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+    fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+}
+
+/// Gamma encoded sRGB, which palette only allows mixing in its linear form.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GammaRgb {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+}
+
+impl Mix for GammaRgb {
+    type Scalar = f32;
+
+    fn mix(&self, other: &GammaRgb, factor: f32) -> GammaRgb {
+        let factor = factor.clamp(0.0, 1.0);
+        GammaRgb {
+            red: self.red + factor * (other.red - self.red),
+            green: self.green + factor * (other.green - self.green),
+            blue: self.blue + factor * (other.blue - self.blue),
+        }
+    }
+}
+
+/// The Oklab color space by Björn Ottosson.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Oklab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+impl Oklab {
+    /// Converts from linear sRGB.
+    pub fn from_linear(rgb: LinSrgb) -> Oklab {
+        let l = 0.412_221_46 * rgb.red + 0.536_332_5 * rgb.green + 0.051_445_995 * rgb.blue;
+        let m = 0.211_903_5 * rgb.red + 0.680_699_5 * rgb.green + 0.107_396_96 * rgb.blue;
+        let s = 0.088_302_46 * rgb.red + 0.281_718_85 * rgb.green + 0.629_978_7 * rgb.blue;
+
+        let (l, m, s) = (l.cbrt(), m.cbrt(), s.cbrt());
+
+        Oklab {
+            l: 0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+            a: 1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+            b: 0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+        }
+    }
+
+    /// Converts to linear sRGB. The result is not clamped.
+    pub fn into_linear(self) -> LinSrgb {
+        let l = self.l + 0.396_337_78 * self.a + 0.215_803_76 * self.b;
+        let m = self.l - 0.105_561_346 * self.a - 0.063_854_17 * self.b;
+        let s = self.l - 0.089_484_18 * self.a - 1.291_485_5 * self.b;
+
+        let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+
+        LinSrgb::new(
+            4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+            -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+            -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+        )
+    }
+}
+
+impl Mix for Oklab {
+    type Scalar = f32;
+
+    fn mix(&self, other: &Oklab, factor: f32) -> Oklab {
+        let factor = factor.clamp(0.0, 1.0);
+        Oklab {
+            l: self.l + factor * (other.l - self.l),
+            a: self.a + factor * (other.a - self.a),
+            b: self.b + factor * (other.b - self.b),
+        }
+    }
+}
+
+impl From<Oklch> for Oklab {
+    fn from(color: Oklch) -> Oklab {
+        let (sin, cos) = color.hue.to_radians().sin_cos();
+        Oklab {
+            l: color.l,
+            a: color.chroma * cos,
+            b: color.chroma * sin,
+        }
+    }
+}
+
+/// The cylindrical form of Oklab, with hue in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Oklch {
+    pub l: f32,
+    pub chroma: f32,
+    pub hue: f32,
+}
+
+impl Mix for Oklch {
+    type Scalar = f32;
+
+    /// Hue follows the shorter arc, the same as palette's `Lch`.
+    fn mix(&self, other: &Oklch, factor: f32) -> Oklch {
+        let factor = factor.clamp(0.0, 1.0);
+        let mut hue_diff = (other.hue - self.hue).rem_euclid(360.0);
+        if hue_diff > 180.0 {
+            hue_diff -= 360.0;
+        }
+        Oklch {
+            l: self.l + factor * (other.l - self.l),
+            chroma: self.chroma + factor * (other.chroma - self.chroma),
+            hue: (self.hue + factor * hue_diff).rem_euclid(360.0),
+        }
+    }
+}
+
+impl From<Oklab> for Oklch {
+    fn from(color: Oklab) -> Oklch {
+        Oklch {
+            l: color.l,
+            chroma: (color.a * color.a + color.b * color.b).sqrt(),
+            hue: color.b.atan2(color.a).to_degrees().rem_euclid(360.0),
+        }
+    }
+}
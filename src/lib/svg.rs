@@ -0,0 +1,239 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+
+use palette::Srgba;
+
+use super::cvd::{simulate, Simulation};
+use super::diff::delta_e76_linear;
+use super::gamut::Gamut;
+use crate::ColorGradient;
+
+/// Deepest subdivision when sampling a continuous gradient, about 4096 stops
+/// per segment.
+const MAX_DEPTH: u32 = 12;
+
+/// Formats a color as an SVG fill or stop color and its opacity.
+fn svg_color(color: Srgba) -> (String, f32) {
+    let c = color.into_format::<u8, f32>();
+    (format!("#{:x}", c.color), c.alpha)
+}
+
+/// Mixes gamma encoded sRGB like an SVG renderer interpolates gradient stops.
+fn mix_srgb(a: Srgba, b: Srgba, factor: f32) -> Srgba {
+    Srgba::new(
+        a.red + factor * (b.red - a.red),
+        a.green + factor * (b.green - a.green),
+        a.blue + factor * (b.blue - a.blue),
+        a.alpha + factor * (b.alpha - a.alpha),
+    )
+}
+
+/// Adds stops between `start` and `end` until an SVG renderer's sRGB
+/// interpolation between neighboring stops stays within `tolerance` ΔE of the
+/// gradient.
+fn subdivide(
+    grad: &ColorGradient,
+    gamut: &Gamut,
+    start: (f32, Srgba),
+    end: (f32, Srgba),
+    tolerance: f32,
+    depth: u32,
+    stops: &mut Vec<(f32, Srgba)>,
+) {
+    let within = [0.25, 0.5, 0.75].iter().all(|&factor| {
+        let expected = gamut.map(grad.get(start.0 + factor * (end.0 - start.0)));
+        let approx = mix_srgb(start.1, end.1, factor).into_linear();
+        delta_e76_linear(expected.color, approx.color) <= tolerance
+            && (expected.alpha - approx.alpha).abs() <= 0.01
+    });
+    if within || depth >= MAX_DEPTH {
+        stops.push(end);
+        return;
+    }
+
+    let mid_pos = 0.5 * (start.0 + end.0);
+    let mid = (mid_pos, gamut.to_srgb(grad.get(mid_pos)));
+    subdivide(grad, gamut, start, mid, tolerance, depth + 1, stops);
+    subdivide(grad, gamut, mid, end, tolerance, depth + 1, stops);
+}
+
+/// Samples stops from the gradient, always including each input stop.
+fn sample_stops(grad: &ColorGradient, gamut: &Gamut, tolerance: f32) -> Vec<(f32, Srgba)> {
+    let sample = |pos: f32| (pos, gamut.to_srgb(grad.get(pos)));
+
+    let mut breaks = vec![0.0];
+    breaks.extend(grad.positions().iter().filter(|&&p| p > 0.0 && p < 1.0));
+    breaks.push(1.0);
+
+    let mut stops = vec![sample(0.0)];
+    for pair in breaks.windows(2) {
+        // Sample just before each break so hard transitions from easing stay
+        // sharp.
+        let end = sample(pair[1]);
+        let before = sample((pair[1] - 1e-4).max(pair[0]));
+        let start = *stops.last().unwrap();
+        subdivide(grad, gamut, start, before, tolerance, 0, &mut stops);
+        stops.push(end);
+    }
+    stops.dedup_by(|a, b| a.0 == b.0);
+    stops
+}
+
+/// Writes a linear, continuous gradient as an SVG `<linearGradient>`. Stops
+/// are sampled from the original colors and then simulated, if at all.
+pub(crate) fn linear_gradient_svg(
+    grad: &ColorGradient,
+    gamut: &Gamut,
+    simulation: Option<Simulation>,
+    size: (u32, u32),
+    tolerance: f32,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut svg = header(size.0, size.1);
+    svg.push_str("  <defs>\n");
+    svg.push_str("    <linearGradient id=\"gradient\" x1=\"0\" y1=\"0\" x2=\"1\" y2=\"0\">\n");
+    for (offset, color) in sample_stops(grad, gamut, tolerance) {
+        let (hex, opacity) = svg_color(simulate(simulation, color));
+        writeln!(
+            svg,
+            "      <stop offset=\"{:.5}\" stop-color=\"{}\" stop-opacity=\"{:.3}\"/>",
+            offset, hex, opacity
+        )?;
+    }
+    svg.push_str("    </linearGradient>\n");
+    svg.push_str("  </defs>\n");
+    writeln!(
+        svg,
+        "  <rect width=\"{}\" height=\"{}\" fill=\"url(#gradient)\"/>",
+        size.0, size.1
+    )?;
+    svg.push_str("</svg>\n");
+
+    std::fs::write(path, svg)?;
+    Ok(())
+}
+
+/// Writes a linear, stepped gradient as one `<rect>` per swatch.
+pub(crate) fn linear_stepped_svg(
+    colors: &[Srgba],
+    swatch_size: (u32, u32),
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let (sw, sh) = swatch_size;
+    let mut svg = header(sw * colors.len() as u32, sh);
+    for (i, &color) in colors.iter().enumerate() {
+        let (hex, opacity) = svg_color(color);
+        writeln!(
+            svg,
+            "  <rect x=\"{}\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"{}\"{}/>",
+            i as u32 * sw,
+            sw,
+            sh,
+            hex,
+            fill_opacity(opacity)
+        )?;
+    }
+    svg.push_str("</svg>\n");
+
+    std::fs::write(path, svg)?;
+    Ok(())
+}
+
+/// Writes a radial, stepped gradient as one annular sector `<path>` per
+/// swatch. Sectors cover the same angles as the raster output, where color `k`
+/// is centered on `k / (colors.len() - 1)` of a turn.
+pub(crate) fn radial_stepped_svg(
+    colors: &[Srgba],
+    size: u32,
+    radius_inner: f32,
+    angle_offset: f32,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let tau = core::f32::consts::PI * 2.0;
+    let center = size as f32 * 0.5;
+    let outer = size as f32 * 0.5;
+    let inner = size as f32 * radius_inner;
+    let grad_len = (colors.len() - 1).max(1) as f32;
+
+    let mut svg = header(size, size);
+    for (k, &color) in colors.iter().enumerate() {
+        let from = ((k as f32 - 0.5) / grad_len).max(0.0);
+        let to = ((k as f32 + 0.5) / grad_len).min(1.0);
+        if to <= from {
+            continue;
+        }
+        // Turn fractions are measured from `angle_offset`, see `radial.rs`.
+        let angle = |f: f32| f * tau - angle_offset;
+        let angles = [angle(from), angle(0.5 * (from + to)), angle(to)];
+        let point = |r: f32, a: f32| (center + r * a.cos(), center + r * a.sin());
+
+        let mut d = String::new();
+        let o = angles.iter().map(|&a| point(outer, a)).collect::<Vec<_>>();
+        write!(d, "M{:.3},{:.3}", o[0].0, o[0].1)?;
+        write!(
+            d,
+            "A{r:.3},{r:.3} 0 0 1 {:.3},{:.3}",
+            o[1].0,
+            o[1].1,
+            r = outer
+        )?;
+        write!(
+            d,
+            "A{r:.3},{r:.3} 0 0 1 {:.3},{:.3}",
+            o[2].0,
+            o[2].1,
+            r = outer
+        )?;
+        if inner > 0.0 {
+            let i = angles.iter().map(|&a| point(inner, a)).collect::<Vec<_>>();
+            write!(d, "L{:.3},{:.3}", i[2].0, i[2].1)?;
+            write!(
+                d,
+                "A{r:.3},{r:.3} 0 0 0 {:.3},{:.3}",
+                i[1].0,
+                i[1].1,
+                r = inner
+            )?;
+            write!(
+                d,
+                "A{r:.3},{r:.3} 0 0 0 {:.3},{:.3}",
+                i[0].0,
+                i[0].1,
+                r = inner
+            )?;
+        } else {
+            write!(d, "L{:.3},{:.3}", center, center)?;
+        }
+        d.push('Z');
+
+        let (hex, opacity) = svg_color(color);
+        writeln!(
+            svg,
+            "  <path d=\"{}\" fill=\"{}\"{}/>",
+            d,
+            hex,
+            fill_opacity(opacity)
+        )?;
+    }
+    svg.push_str("</svg>\n");
+
+    std::fs::write(path, svg)?;
+    Ok(())
+}
+
+fn fill_opacity(opacity: f32) -> String {
+    if opacity < 1.0 {
+        format!(" fill-opacity=\"{:.3}\"", opacity)
+    } else {
+        String::new()
+    }
+}
+
+fn header(width: u32, height: u32) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = width,
+        h = height
+    )
+}
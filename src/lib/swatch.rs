@@ -0,0 +1,559 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use palette::chromatic_adaptation::AdaptInto;
+use palette::white_point::{D50, D65};
+use palette::{Lab, Lcha, Srgb, Srgba};
+
+use super::analysis::report_steps;
+use super::color::parse_color_at;
+use super::contrast::report_contrast;
+use super::gamut::Gamut;
+use crate::{print_colors, save_palette, ColorStop, Config, Notation};
+
+/// Palette files which stepped gradient colors can be saved to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaletteFormat {
+    /// Adobe Swatch Exchange, read by Illustrator, Photoshop and InDesign.
+    Ase,
+    /// Photoshop color swatches.
+    Aco,
+    /// GIMP palette, read by GIMP, Krita and Inkscape.
+    Gpl,
+}
+
+impl PaletteFormat {
+    /// The default file extension for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            PaletteFormat::Ase => "ase",
+            PaletteFormat::Aco => "aco",
+            PaletteFormat::Gpl => "gpl",
+        }
+    }
+}
+
+impl FromStr for PaletteFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ase" => Ok(PaletteFormat::Ase),
+            "aco" => Ok(PaletteFormat::Aco),
+            "gpl" => Ok(PaletteFormat::Gpl),
+            _ => Err(format!("Unsupported palette format `{}`", s)),
+        }
+    }
+}
+
+/// The name of each palette entry, its hex value.
+fn entry_name(color: &Srgba) -> String {
+    format!("{:x}", color.color.into_format::<u8>())
+}
+
+/// Writes the colors of a stepped gradient as a GIMP palette. Each entry is
+/// named after its hex value, the alpha channel is dropped since the format
+/// has none.
+pub(crate) fn write_gpl(
+    colors: &[Srgba],
+    name: &str,
+    columns: usize,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut gpl = String::from("GIMP Palette\n");
+    writeln!(gpl, "Name: {}", name.lines().next().unwrap_or_default())?;
+    writeln!(gpl, "Columns: {}", columns)?;
+    gpl.push_str("#\n");
+    for color in colors {
+        let c = color.color.into_format::<u8>();
+        writeln!(
+            gpl,
+            "{:3} {:3} {:3}\t{}",
+            c.red,
+            c.green,
+            c.blue,
+            entry_name(color)
+        )?;
+    }
+
+    std::fs::write(path, gpl)?;
+    Ok(())
+}
+
+/// Appends a length prefixed, null terminated UTF-16BE string. ASE counts the
+/// length in 16-bit units with a `u16`, ACO with a `u32`.
+fn push_utf16(buf: &mut Vec<u8>, s: &str, wide_len: bool) {
+    let units: Vec<u16> = s.encode_utf16().chain(Some(0)).collect();
+    if wide_len {
+        buf.extend_from_slice(&(units.len() as u32).to_be_bytes());
+    } else {
+        buf.extend_from_slice(&(units.len() as u16).to_be_bytes());
+    }
+    units
+        .iter()
+        .for_each(|u| buf.extend_from_slice(&u.to_be_bytes()));
+}
+
+/// Appends an ASE block of `kind` with its body's length.
+fn push_ase_block(buf: &mut Vec<u8>, kind: u16, body: &[u8]) {
+    buf.extend_from_slice(&kind.to_be_bytes());
+    buf.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    buf.extend_from_slice(body);
+}
+
+/// Writes the colors of a stepped gradient as an Adobe Swatch Exchange file of
+/// RGB process colors named after their hex values. The colors are wrapped in
+/// a group when `group` is given.
+pub(crate) fn write_ase(
+    colors: &[Srgba],
+    group: Option<&str>,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let blocks = colors.len() + if group.is_some() { 2 } else { 0 };
+    let mut ase = b"ASEF".to_vec();
+    ase.extend_from_slice(&1u16.to_be_bytes());
+    ase.extend_from_slice(&0u16.to_be_bytes());
+    ase.extend_from_slice(&(blocks as u32).to_be_bytes());
+
+    if let Some(group) = group {
+        let mut body = Vec::new();
+        push_utf16(&mut body, group, false);
+        push_ase_block(&mut ase, 0xc001, &body);
+    }
+    for color in colors {
+        let mut body = Vec::new();
+        push_utf16(&mut body, &entry_name(color), false);
+        body.extend_from_slice(b"RGB ");
+        for &c in [color.red, color.green, color.blue].iter() {
+            body.extend_from_slice(&c.clamp(0.0, 1.0).to_be_bytes());
+        }
+        // Normal, as opposed to global or spot, colors.
+        body.extend_from_slice(&2u16.to_be_bytes());
+        push_ase_block(&mut ase, 0x0001, &body);
+    }
+    if group.is_some() {
+        push_ase_block(&mut ase, 0xc002, &[]);
+    }
+
+    std::fs::write(path, ase)?;
+    Ok(())
+}
+
+/// Writes the colors of a stepped gradient as Photoshop color swatches. The
+/// version 1 section is followed by a version 2 section which adds the
+/// names, so older readers still find the colors.
+pub(crate) fn write_aco(colors: &[Srgba], path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut aco = Vec::new();
+    for &version in [1u16, 2].iter() {
+        aco.extend_from_slice(&version.to_be_bytes());
+        aco.extend_from_slice(&(colors.len() as u16).to_be_bytes());
+        for color in colors {
+            let c = color.color.into_format::<u16>();
+            // Color space 0 is RGB, the fourth component is unused.
+            for &word in [0, c.red, c.green, c.blue, 0].iter() {
+                aco.extend_from_slice(&word.to_be_bytes());
+            }
+            if version == 2 {
+                push_utf16(&mut aco, &entry_name(color), true);
+            }
+        }
+    }
+
+    std::fs::write(path, aco)?;
+    Ok(())
+}
+
+/// Prints the input colors, or saves them with `--palette`, without making a
+/// gradient. This turns extracted colors and palette files into other
+/// palette formats.
+pub fn palette_only(config: Config) -> Result<(), Box<dyn Error>> {
+    let gamut = Gamut::new(&config);
+    let colors: Vec<Srgba> = config
+        .grad_vec
+        .iter()
+        .map(|&c| gamut.to_srgb(c.into()))
+        .collect();
+    gamut.report(config.gamut_report, "colors");
+
+    // Colors are printed when there's no other output.
+    if config.print_grad
+        || (config.palette_file.is_none() && config.contrast.is_none() && config.analyze.is_none())
+    {
+        print_colors(&colors);
+    }
+    report_contrast(&config, &colors)?;
+    report_steps(&config, &colors)?;
+    save_palette(&config, &colors)
+}
+
+/// Reads the colors of a palette file to use as gradient input. The format
+/// is chosen by extension: GIMP `.gpl`, Lospec `.hex`, JASC `.pal` or Adobe
+/// `.ase`. Any other file is read as text with one color per line, either
+/// hex or `R,G,B`, where blank lines and lines starting with `;` or `//` are
+/// skipped.
+pub fn read_palette(path: &Path) -> Result<Vec<ColorStop>, Box<dyn Error>> {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Could not read palette {}: {}", path.display(), e))?;
+    let text = || String::from_utf8_lossy(&bytes);
+
+    let stops = match ext.as_deref() {
+        Some("ase") => read_ase(&bytes)
+            .ok_or_else(|| format!("{} is not a valid ASE palette", path.display()))?
+            .into_iter()
+            .map(|color| ColorStop {
+                color,
+                position: None,
+            })
+            .collect(),
+        Some("gpl") => read_gpl(&text(), path)?,
+        Some("pal") => read_pal(&text(), path)?,
+        Some("hex") => read_lines(&text(), |_| Notation::Hex)?,
+        _ => read_lines(&text(), |line| {
+            if line.contains(',') {
+                Notation::Rgb
+            } else {
+                Notation::Hex
+            }
+        })?,
+    };
+
+    if stops.is_empty() {
+        return Err(format!("No colors found in palette {}", path.display()).into());
+    }
+    Ok(stops)
+}
+
+/// Parses each color line of a text palette in the notation picked for it.
+fn read_lines<F>(text: &str, notation: F) -> Result<Vec<ColorStop>, Box<dyn Error>>
+where
+    F: Fn(&str) -> Notation,
+{
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(';') && !line.starts_with("//"))
+        .enumerate()
+        .map(|(i, line)| {
+            Ok(parse_color_at(
+                "--palette-file",
+                i + 1,
+                notation(line),
+                line,
+            )?)
+        })
+        .collect()
+}
+
+/// Parses the leading `R G B` of each entry line of a GIMP or JASC palette.
+fn read_rgb_lines<'a, I>(lines: I) -> Result<Vec<ColorStop>, Box<dyn Error>>
+where
+    I: Iterator<Item = &'a str>,
+{
+    lines
+        .enumerate()
+        .map(|(i, line)| {
+            let rgb = line
+                .split_whitespace()
+                .take(3)
+                .collect::<Vec<_>>()
+                .join(",");
+            Ok(parse_color_at(
+                "--palette-file",
+                i + 1,
+                Notation::Rgb,
+                &rgb,
+            )?)
+        })
+        .collect()
+}
+
+/// Reads a GIMP palette, skipping its header and comments.
+fn read_gpl(text: &str, path: &Path) -> Result<Vec<ColorStop>, Box<dyn Error>> {
+    let mut lines = text.lines().map(str::trim);
+    if lines.next() != Some("GIMP Palette") {
+        return Err(format!("{} is not a valid GIMP palette", path.display()).into());
+    }
+    read_rgb_lines(lines.filter(|line| {
+        !line.is_empty()
+            && !line.starts_with('#')
+            && !line.starts_with("Name:")
+            && !line.starts_with("Columns:")
+    }))
+}
+
+/// Reads a JASC palette, as written by Paint Shop Pro and Aseprite.
+fn read_pal(text: &str, path: &Path) -> Result<Vec<ColorStop>, Box<dyn Error>> {
+    let mut lines = text.lines().map(str::trim);
+    let count = match (lines.next(), lines.next(), lines.next()) {
+        (Some("JASC-PAL"), Some(_), Some(count)) => count.parse::<usize>().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{} is not a valid JASC palette", path.display()))?;
+    read_rgb_lines(lines.filter(|line| !line.is_empty()).take(count))
+}
+
+fn read_u16(buf: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*buf.get(at)?, *buf.get(at + 1)?]))
+}
+
+fn read_u32(buf: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes([
+        *buf.get(at)?,
+        *buf.get(at + 1)?,
+        *buf.get(at + 2)?,
+        *buf.get(at + 3)?,
+    ]))
+}
+
+fn read_f32(buf: &[u8], at: usize) -> Option<f32> {
+    read_u32(buf, at).map(f32::from_bits)
+}
+
+/// Reads the color entries of an ASE file, skipping groups. RGB, gray, Lab
+/// and CMYK colors are read, where Lab is relative to D50 and CMYK is
+/// converted naively without a color profile.
+fn read_ase(buf: &[u8]) -> Option<Vec<Lcha<D65>>> {
+    if buf.get(0..4)? != b"ASEF" {
+        return None;
+    }
+    let blocks = read_u32(buf, 8)?;
+
+    let mut colors = Vec::new();
+    let mut at = 12;
+    for _ in 0..blocks {
+        let kind = read_u16(buf, at)?;
+        let len = read_u32(buf, at + 2)? as usize;
+        let body = buf.get(at + 6..at + 6 + len)?;
+        at += 6 + len;
+        if kind != 0x0001 {
+            continue;
+        }
+
+        // Skip the length prefixed UTF-16 name.
+        let model = 2 + 2 * read_u16(body, 0)? as usize;
+        let value = |i: usize| read_f32(body, model + 4 + 4 * i);
+        let color = match body.get(model..model + 4)? {
+            b"RGB " => {
+                let rgb = Srgb::new(value(0)?, value(1)?, value(2)?);
+                Lcha::from(rgb.into_linear())
+            }
+            b"Gray" => {
+                let gray = value(0)?;
+                Lcha::from(Srgb::new(gray, gray, gray).into_linear())
+            }
+            b"LAB " => {
+                let lab = Lab::<D50>::with_wp(value(0)? * 100.0, value(1)?, value(2)?);
+                let lab: Lab<D65> = lab.adapt_into();
+                Lcha::from(lab)
+            }
+            b"CMYK" => {
+                let k = 1.0 - value(3)?;
+                let rgb = Srgb::new(
+                    (1.0 - value(0)?) * k,
+                    (1.0 - value(1)?) * k,
+                    (1.0 - value(2)?) * k,
+                );
+                Lcha::from(rgb.into_linear())
+            }
+            _ => return None,
+        };
+        colors.push(color);
+    }
+    Some(colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryInto;
+
+    use palette::LinSrgba;
+
+    fn u16_at(buf: &[u8], at: usize) -> u16 {
+        u16::from_be_bytes(buf[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(buf: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes(buf[at..at + 4].try_into().unwrap())
+    }
+
+    /// Reads a string written by `push_utf16`, returning it and its size.
+    fn utf16_at(buf: &[u8], at: usize, wide_len: bool) -> (String, usize) {
+        let (len, start) = if wide_len {
+            (u32_at(buf, at) as usize, at + 4)
+        } else {
+            (u16_at(buf, at) as usize, at + 2)
+        };
+        let units: Vec<u16> = (0..len).map(|i| u16_at(buf, start + 2 * i)).collect();
+        let s = String::from_utf16(&units[..len - 1]).unwrap();
+        (s, start + 2 * len - at)
+    }
+
+    fn colors() -> Vec<Srgba> {
+        vec![
+            Srgba::new(232u8, 83, 72, 255).into_format(),
+            Srgba::new(70u8, 143, 70, 255).into_format(),
+            Srgba::new(34u8, 16, 110, 255).into_format(),
+        ]
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("palgrad-{}-{}", std::process::id(), name))
+    }
+
+    /// Converts colors read from a palette back to 8-bit sRGB.
+    fn to_rgb<I: IntoIterator<Item = Lcha<D65>>>(colors: I) -> Vec<[u8; 3]> {
+        colors
+            .into_iter()
+            .map(|c| {
+                let rgb: Srgba<u8> = Srgba::from_linear(LinSrgba::from(c)).into_format();
+                [rgb.red, rgb.green, rgb.blue]
+            })
+            .collect()
+    }
+
+    type AcoSection = (u16, Vec<(Option<String>, [u8; 3])>);
+
+    /// Reads each version section of an ACO file, with names for version 2.
+    fn read_aco(buf: &[u8]) -> Vec<AcoSection> {
+        let mut sections = Vec::new();
+        let mut at = 0;
+        while at < buf.len() {
+            let version = u16_at(buf, at);
+            let count = u16_at(buf, at + 2);
+            at += 4;
+            let mut colors = Vec::new();
+            for _ in 0..count {
+                assert_eq!(u16_at(buf, at), 0);
+                let mut rgb = [0; 3];
+                for (i, c) in rgb.iter_mut().enumerate() {
+                    *c = (u16_at(buf, at + 2 + 2 * i) / 257) as u8;
+                }
+                at += 10;
+                let name = if version == 2 {
+                    let (name, size) = utf16_at(buf, at, true);
+                    at += size;
+                    Some(name)
+                } else {
+                    None
+                };
+                colors.push((name, rgb));
+            }
+            sections.push((version, colors));
+        }
+        sections
+    }
+
+    #[test]
+    fn ase_round_trip() {
+        let path = temp_path("group.ase");
+        write_ase(&colors(), Some("Sunset"), &path).unwrap();
+        let buf = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // The colors are wrapped in a group start and end block.
+        assert_eq!(read_u32(&buf, 8), Some(5));
+        assert_eq!(read_u16(&buf, 12), Some(0xc001));
+        assert_eq!(read_u16(&buf, buf.len() - 6), Some(0xc002));
+        assert_eq!(
+            to_rgb(read_ase(&buf).unwrap()),
+            vec![[232, 83, 72], [70, 143, 70], [34, 16, 110]]
+        );
+    }
+
+    #[test]
+    fn ase_without_group() {
+        let path = temp_path("plain.ase");
+        write_ase(&colors(), None, &path).unwrap();
+        let buf = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read_u32(&buf, 8), Some(3));
+        assert_eq!(read_u16(&buf, 12), Some(0x0001));
+        assert_eq!(to_rgb(read_ase(&buf).unwrap()).len(), 3);
+    }
+
+    #[test]
+    fn ase_rejects_other_files() {
+        assert!(read_ase(b"GIMP Palette").is_none());
+        // The header promises a block that isn't there.
+        assert!(read_ase(b"ASEF\0\x01\0\0\0\0\0\x01").is_none());
+    }
+
+    #[test]
+    fn aco_round_trip() {
+        let path = temp_path("swatches.aco");
+        write_aco(&colors(), &path).unwrap();
+        let sections = read_aco(&std::fs::read(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let rgb = [[232, 83, 72], [70, 143, 70], [34, 16, 110]];
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].0, 1);
+        assert_eq!(sections[1].0, 2);
+        for (i, &rgb) in rgb.iter().enumerate() {
+            assert_eq!(sections[0].1[i], (None, rgb));
+            assert_eq!(sections[1].1[i].1, rgb);
+        }
+        assert_eq!(sections[1].1[0].0.as_deref(), Some("e85348"));
+    }
+
+    /// Writes `text` to a palette file named `name` and reads it back.
+    fn read_text(name: &str, text: &str) -> Result<Vec<[u8; 3]>, String> {
+        let path = temp_path(name);
+        std::fs::write(&path, text).unwrap();
+        let stops = read_palette(&path);
+        std::fs::remove_file(&path).unwrap();
+        stops
+            .map(|stops| to_rgb(stops.into_iter().map(|s| s.color)))
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn gpl_palette() {
+        let text = "GIMP Palette\nName: Sunset\nColumns: 2\n#\n232  83  72\te85348\n 70 143  70\n\n# A comment\n 34  16 110 Untitled\n";
+        assert_eq!(
+            read_text("sunset.gpl", text).unwrap(),
+            vec![[232, 83, 72], [70, 143, 70], [34, 16, 110]]
+        );
+        assert!(read_text("bad.gpl", "232 83 72\n").is_err());
+    }
+
+    #[test]
+    fn pal_palette() {
+        let text = "JASC-PAL\r\n0100\r\n2\r\n232 83 72\r\n70 143 70\r\n34 16 110\r\n";
+        assert_eq!(
+            read_text("sunset.pal", text).unwrap(),
+            vec![[232, 83, 72], [70, 143, 70]]
+        );
+        assert!(read_text("bad.pal", "JASC-PAL\n0100\n").is_err());
+    }
+
+    #[test]
+    fn hex_palette() {
+        assert_eq!(
+            read_text("sunset.hex", "e85348\n468F46\n\n22106e\n").unwrap(),
+            vec![[232, 83, 72], [70, 143, 70], [34, 16, 110]]
+        );
+        let err = read_text("bad.hex", "e85348\n46 8f 46\n").unwrap_err();
+        assert!(err.contains("color 2"), "{}", err);
+    }
+
+    #[test]
+    fn text_palette() {
+        let text =
+            "; Sunset\n#e85348\n// Rgb lines are detected by their commas\n70, 143, 70\n#22106e\n";
+        assert_eq!(
+            read_text("sunset.txt", text).unwrap(),
+            vec![[232, 83, 72], [70, 143, 70], [34, 16, 110]]
+        );
+        assert!(read_text("empty.txt", "; Nothing here\n").is_err());
+    }
+}
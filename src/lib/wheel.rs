@@ -0,0 +1,218 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use palette::white_point::D65;
+use palette::{Lcha, LinSrgba, Pixel, Srgba};
+
+use super::analysis::report_steps;
+use super::contrast::report_contrast;
+use super::cvd::simulate;
+use super::dither::Quantizer;
+use super::gamut::Gamut;
+use super::radial::midpoint_xy_dist;
+use crate::{output_path, print_colors, save_image_alpha, save_palette, ColorGradient, Config};
+
+/// The `Lch` channel which the radius of a wheel controls.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WheelChannel {
+    Lightness,
+    Chroma,
+}
+
+impl WheelChannel {
+    /// The channel values at the inner and outer radius when no range is
+    /// given: light to dark, or grey to saturated.
+    pub fn default_range(self) -> (f32, f32) {
+        match self {
+            WheelChannel::Lightness => (90.0, 30.0),
+            WheelChannel::Chroma => (0.0, 80.0),
+        }
+    }
+
+    /// Replaces the channel of `color` with `value` in `Lch`.
+    fn apply(self, color: LinSrgba, value: f32) -> LinSrgba {
+        let mut lch = Lcha::<D65>::from(color);
+        match self {
+            WheelChannel::Lightness => lch.l = value,
+            WheelChannel::Chroma => lch.chroma = value.max(0.0),
+        }
+        LinSrgba::from(lch)
+    }
+}
+
+impl fmt::Display for WheelChannel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WheelChannel::Lightness => write!(f, "lightness"),
+            WheelChannel::Chroma => write!(f, "chroma"),
+        }
+    }
+}
+
+impl FromStr for WheelChannel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "l" | "lightness" => Ok(WheelChannel::Lightness),
+            "c" | "chroma" => Ok(WheelChannel::Chroma),
+            _ => Err(format!("Unsupported wheel channel `{}`", s)),
+        }
+    }
+}
+
+/// Returns the angle of `dist` from the center as a factor of a full turn,
+/// starting at `angle_offset` radians.
+pub(crate) fn angle_factor(dist: [f32; 2], angle_offset: f32) -> f32 {
+    let tau = core::f32::consts::PI * 2.0;
+    (dist[1].atan2(dist[0]) + angle_offset).rem_euclid(tau) / tau
+}
+
+/// Creates an image of a continuous two-axis wheel. The angle samples the
+/// gradient and the radius sets the wheel channel between its two bounds.
+pub fn wheel_gradient_continuous(config: Config) -> Result<(), Box<dyn Error>> {
+    let grad = ColorGradient::new(&config);
+    let gamut = Gamut::new(&config);
+    let channel = config.wheel_channel;
+    let (inner, outer) = config.wheel_range;
+    let img_x = config.size;
+    let img_y = config.size;
+    let mut imgbuf: image::RgbaImage = image::ImageBuffer::new(img_x, img_y);
+    let mut quantizer = Quantizer::new(config.dither, img_x, config.seed);
+    let rad_outer = config.size as f32 * 0.5;
+    let rad_inner = config.size as f32 * config.radius_inner;
+
+    let mut pix: [u8; 4];
+    let mut dist: [f32; 2];
+    let mut radius;
+    let mut value;
+
+    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
+        dist = midpoint_xy_dist(img_x, img_y, x, y);
+        radius = (dist[0] * dist[0] + dist[1] * dist[1]).sqrt();
+        if radius >= rad_inner && radius <= rad_outer {
+            value = inner + (outer - inner) * (radius - rad_inner) / (rad_outer - rad_inner);
+            let color = grad.get(angle_factor(dist, config.angle_offset));
+            let color = gamut.to_srgb(channel.apply(color, value));
+            pix = quantizer.quantize(x, y, simulate(config.simulate, color));
+        } else {
+            pix = [0, 0, 0, 0];
+        }
+        *pixel = image::Rgba(pix);
+    }
+    gamut.report(config.gamut_report, "pixels");
+
+    if let Some(mask) = &config.mask {
+        mask.apply(&mut imgbuf, config.angle_offset);
+    }
+    let title = output_path(config.output_file.as_deref(), config.format)?;
+
+    save_image_alpha(&imgbuf, &title, config.format)
+}
+
+/// Creates an image of a stepped two-axis wheel, made of cells with `steps`
+/// hues around and `rings` channel values out from the center. With
+/// `--print`, each ring's colors are printed on their own line.
+pub fn wheel_gradient_stepped(config: Config) -> Result<(), Box<dyn Error>> {
+    // The starting color is appended to the end for radial gradients, see
+    // `radial_gradient_stepped`.
+    let steps = config.steps + 1;
+    let rings = config.rings.max(1);
+    let rad_factor_inner = config.radius_inner * 2.0;
+    let channel = config.wheel_channel;
+    let (inner, outer) = config.wheel_range;
+
+    let grad = ColorGradient::new(&config);
+    let hues = grad.take_spaced(steps, config.spacing);
+    config.spacing.report(&hues);
+    let gamut = Gamut::new(&config);
+
+    let mut cells: Vec<Vec<Srgba>> = Vec::with_capacity(rings);
+    for ring in 0..rings {
+        let factor = if rings > 1 {
+            ring as f32 / (rings - 1) as f32
+        } else {
+            0.0
+        };
+        let value = inner + (outer - inner) * factor;
+        cells.push(
+            hues.iter()
+                .map(|&c| gamut.to_srgb(channel.apply(c, value)))
+                .collect(),
+        );
+    }
+    gamut.report(config.gamut_report, "cells");
+
+    // The last hue repeats the first to close the circle and only the cells
+    // with their center inside a gamut mask are printed and saved.
+    let grad_len = (steps - 1) as f32;
+    let rows: Vec<Vec<Srgba>> = cells
+        .iter()
+        .enumerate()
+        .map(|(ring, colors)| {
+            let radius =
+                rad_factor_inner + (1.0 - rad_factor_inner) * (ring as f32 + 0.5) / rings as f32;
+            colors[..config.steps]
+                .iter()
+                .enumerate()
+                .filter(|&(hue, _)| match &config.mask {
+                    Some(mask) => mask.contains(hue as f32 / grad_len, radius, config.angle_offset),
+                    None => true,
+                })
+                .map(|(_, &c)| c)
+                .collect()
+        })
+        .collect();
+    if config.print_grad {
+        rows.iter().for_each(|row| print_colors(row));
+    }
+    // Reports and palettes get the cells ring by ring, from the center out.
+    let palette = rows.concat();
+    report_contrast(&config, &palette)?;
+    report_steps(&config, &palette)?;
+    save_palette(&config, &palette)?;
+    if config.no_file {
+        return Ok(());
+    }
+    let cells: Vec<Vec<Srgba>> = cells
+        .iter()
+        .map(|colors| {
+            colors
+                .iter()
+                .map(|&c| simulate(config.simulate, c))
+                .collect()
+        })
+        .collect();
+
+    let img_x = config.size;
+    let img_y = config.size;
+    let mut imgbuf: image::RgbaImage = image::ImageBuffer::new(img_x, img_y);
+    let rad_outer = config.size as f32 * 0.5;
+    let rad_inner = config.size as f32 * config.radius_inner;
+
+    let mut pix: [u8; 4];
+    let mut dist: [f32; 2];
+    let mut radius;
+    let mut ring;
+
+    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
+        dist = midpoint_xy_dist(img_x, img_y, x, y);
+        radius = (dist[0] * dist[0] + dist[1] * dist[1]).sqrt();
+        if radius >= rad_inner && radius <= rad_outer {
+            ring = ((radius - rad_inner) / (rad_outer - rad_inner) * rings as f32) as usize;
+            let hue = (angle_factor(dist, config.angle_offset) * grad_len).round() as usize;
+            pix = cells[ring.min(rings - 1)][hue].into_format().into_raw();
+        } else {
+            pix = [0, 0, 0, 0];
+        }
+        *pixel = image::Rgba(pix);
+    }
+
+    if let Some(mask) = &config.mask {
+        mask.apply(&mut imgbuf, config.angle_offset);
+    }
+    let title = output_path(config.output_file.as_deref(), config.format)?;
+
+    save_image_alpha(&imgbuf, &title, config.format)
+}
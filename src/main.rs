@@ -1,9 +1,10 @@
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
 
-use clap::{crate_version, App, Arg};
-use palette::{Hsv, Lch, LinSrgba, Srgb};
+use clap::{crate_version, App, Arg, ArgMatches};
+use palette::white_point::D65;
+use palette::{Lcha, LinSrgba, Srgb, Srgba};
 
 mod lib;
 use lib::*;
@@ -19,14 +20,18 @@ fn try_main() -> Result<(), Box<dyn Error>> {
     let m = App::new("palgrad")
         .version(crate_version!())
         .about("Create gradients and palettes from the command-line")
+        .after_help("Any color can be followed by a stop position from 0.0 to 1.0, e.g. `228,68,21@0.25`. Missing positions are spaced evenly between known ones.")
+        // A single color is enough for every color flag, as harmonies are
+        // made from one seed color and colors can be combined across flags.
+        // One color on its own makes a solid gradient.
         .arg(
             Arg::with_name("colors")
-                .min_values(2)
+                .min_values(1)
                 .max_values(32)
                 .empty_values(false)
                 .short("c")
                 .long("colors")
-                .help("Specify the colors in `R,G,B` format delimted by `;`")
+                .help("Specify the colors in `R,G,B` or `R,G,B,A` format delimted by `;`, with alpha in the range of 0.0 to 1.0")
                 .value_name("COLORS")
                 .default_value("228,68,21;236,228,38;46,137,209")
                 .require_delimiter(true)
@@ -34,37 +39,37 @@ fn try_main() -> Result<(), Box<dyn Error>> {
         )
         .arg(
             Arg::with_name("decimal colors")
-                .min_values(2)
+                .min_values(1)
                 .max_values(32)
                 .empty_values(false)
                 .short("d")
                 .long("dec")
-                .help("Specify the colors in `R,G,B` format delimted by `;` in the range of 0.0 to 1.0")
+                .help("Specify the colors in `R,G,B` or `R,G,B,A` format delimted by `;` in the range of 0.0 to 1.0")
                 .value_name("DECIMAL_COLORS")
                 .default_value("1.0,0.6,0.0;0.0,0.2,1.0")
                 .require_delimiter(true)
                 .value_delimiter(";"),
         )
-        // .arg(
-        //     Arg::with_name("hex colors")
-        //         .min_values(2)
-        //         .max_values(32)
-        //         .empty_values(false)
-        //         .short("x")
-        //         .long("hex")
-        //         .help("Specify the colors in hex format `#RRGGBB` delimited by `;`")
-        //         .value_name("HEX_COLORS")
-        //         .default_value("#e85348;#468f46;#22106e")
-        //         .require_delimiter(true)
-        //         .value_delimiter(";"),
-        // )
+        .arg(
+            Arg::with_name("hex colors")
+                .min_values(1)
+                .max_values(32)
+                .empty_values(false)
+                .short("x")
+                .long("hex")
+                .help("Specify the colors in hex format `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA` delimited by `;`")
+                .value_name("HEX_COLORS")
+                .default_value("#e85348;#468f46;#22106e")
+                .require_delimiter(true)
+                .value_delimiter(";"),
+        )
         .arg(
             Arg::with_name("hsv colors")
-                .min_values(2)
+                .min_values(1)
                 .max_values(32)
                 .empty_values(false)
                 .long("hsv")
-                .help("Specify the colors in `H,S,V` format delimited by `;`")
+                .help("Specify the colors in `H,S,V` or `H,S,V,A` format delimited by `;`, with alpha in the range of 0.0 to 1.0")
                 .value_name("HSV_COLORS")
                 .default_value("0,80,60;120,70,60;240,80,60")
                 .require_delimiter(true)
@@ -72,16 +77,84 @@ fn try_main() -> Result<(), Box<dyn Error>> {
         )
         .arg(
             Arg::with_name("lch colors")
-                .min_values(2)
+                .min_values(1)
                 .max_values(32)
                 .empty_values(false)
                 .long("lch")
-                .help("Specify the colors in `L,C,h` format delimited by `;`")
+                .help("Specify the colors in `L,C,h` or `L,C,h,A` format delimited by `;`, with alpha in the range of 0.0 to 1.0")
                 .value_name("LCH_COLORS")
                 .default_value("100.0,75.0,0.0;20.0,25.0,200.0")
                 .require_delimiter(true)
                 .value_delimiter(";"),
         )
+        .arg(
+            Arg::with_name("palette file")
+                .long("palette-file")
+                .help("Read the colors from a palette file, `.gpl`, `.hex`, JASC `.pal`, `.ase`, or text with one hex or `R,G,B` color per line")
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("extract")
+                .short("e")
+                .long("extract")
+                .help("Extract the dominant colors of an image to use as the gradient colors")
+                .value_name("IMAGE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("extract count")
+                .long("extract-count")
+                .help("Number of colors to extract")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value("5"),
+        )
+        .arg(
+            Arg::with_name("extract method")
+                .long("extract-method")
+                .help("Clustering of the image colors in Lab")
+                .takes_value(true)
+                .possible_values(&["kmeans", "median-cut"])
+                .case_insensitive(true)
+                .default_value("kmeans"),
+        )
+        .arg(
+            Arg::with_name("extract sort")
+                .long("extract-sort")
+                .help("Order of the extracted colors")
+                .takes_value(true)
+                .possible_values(&["population", "hue"])
+                .case_insensitive(true)
+                .default_value("population"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .help("Seed of the k-means++ initialization and the blue-noise dithering mask")
+                .takes_value(true)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name("harmony")
+                .long("harmony")
+                .help("Replace the colors with a harmony of the first color, rotating its hue in Lch")
+                .takes_value(true)
+                .possible_values(&["complementary", "split-complementary", "analogous", "triadic", "tetradic", "square"])
+                .case_insensitive(true),
+        )
+        .arg(
+            Arg::with_name("harmony sheet")
+                .long("harmony-sheet")
+                .help("Create a contact sheet of every harmony of the first color, one row of swatches each")
+                .conflicts_with_all(&["harmony", "linear", "concentric", "wheel", "palette only"]),
+        )
+        .arg(
+            Arg::with_name("palette only")
+                .long("palette-only")
+                .help("Print or save the input colors with --palette without making a gradient")
+                .conflicts_with_all(&["linear", "concentric", "wheel"]),
+        )
         .arg(
             Arg::with_name("size")
                 .short("s")
@@ -110,6 +183,34 @@ fn try_main() -> Result<(), Box<dyn Error>> {
                 .required(false)
                 .default_value("11"),
         )
+        .arg(
+            Arg::with_name("spacing")
+                .long("spacing")
+                .help("Space the steps evenly in the gradient, or by equal CIEDE2000 or Oklab differences along it, reporting the difference between steps")
+                .takes_value(true)
+                .possible_values(&["even", "de2000", "oklab"])
+                .case_insensitive(true)
+                .default_value("even"),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .help("Bits per channel of continuous linear and radial gradients, 16 is only written to PNG")
+                .value_name("BITS")
+                .takes_value(true)
+                .possible_values(&["8", "16"])
+                .default_value("8"),
+        )
+        .arg(
+            Arg::with_name("dither")
+                .long("dither")
+                .help("Dither continuous gradients before quantizing them to 8 bits, to hide banding")
+                .value_name("METHOD")
+                .takes_value(true)
+                .possible_values(&["none", "bayer", "blue-noise", "floyd-steinberg"])
+                .case_insensitive(true)
+                .default_value("none"),
+        )
         .arg(
             Arg::with_name("overlay")
                 .short("o")
@@ -117,7 +218,7 @@ fn try_main() -> Result<(), Box<dyn Error>> {
                 .min_values(1)
                 .max_values(1)
                 .empty_values(false)
-                .help("Color of overlay in R,G,B")
+                .help("Color of overlay in R,G,B or R,G,B,A")
                 .takes_value(true)
                 .default_value("120,120,120")
                 .require_delimiter(false),
@@ -128,6 +229,64 @@ fn try_main() -> Result<(), Box<dyn Error>> {
                 .last(true)
                 .required(false),
         )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .help("Image format of the output file, inferred from its extension if omitted")
+                .takes_value(true)
+                .possible_values(&["png", "jpeg", "bmp", "tga", "tiff", "pnm", "farbfeld", "ico", "svg"])
+                .case_insensitive(true),
+        )
+        .arg(
+            Arg::with_name("svg tolerance")
+                .long("svg-tolerance")
+                .help("Largest color difference (ΔE) allowed between an SVG linear gradient and the interpolated gradient")
+                .takes_value(true)
+                .default_value("1.0"),
+        )
+        .arg(
+            Arg::with_name("gamut")
+                .long("gamut")
+                .help("Mapping of colors outside of sRGB: clip each channel, reduce Lch chroma at constant lightness and hue, or the CSS Color 4 Oklch algorithm")
+                .takes_value(true)
+                .possible_values(&["clip", "chroma", "css"])
+                .case_insensitive(true)
+                .default_value("clip"),
+        )
+        .arg(
+            Arg::with_name("gamut report")
+                .long("gamut-report")
+                .help("Report how many pixels or steps were out of gamut"),
+        )
+        .arg(
+            Arg::with_name("space")
+                .long("space")
+                .help("Color space the gradient is interpolated in")
+                .takes_value(true)
+                .possible_values(&["lch", "lab", "oklab", "oklch", "linear", "srgb", "hsv", "hsl"])
+                .case_insensitive(true)
+                .default_value("lch"),
+        )
+        .arg(
+            Arg::with_name("hue")
+                .long("hue")
+                .help("Hue interpolation in cylindrical spaces, one method for the whole gradient or one per segment delimited by `,`")
+                .min_values(1)
+                .possible_values(&["shorter", "longer", "increasing", "decreasing"])
+                .case_insensitive(true)
+                .default_value("shorter")
+                .value_delimiter(","),
+        )
+        .arg(
+            Arg::with_name("easing")
+                .long("easing")
+                .help("Easing between stops, one curve for the whole gradient or one per segment delimited by `;`. Curves are linear, smoothstep, ease-in-quad, ease-out-quad, ease-in-out-quad, the same for cubic and sine, ease, ease-in, ease-out, ease-in-out, cubic-bezier(x1,y1,x2,y2), step-start and step-end")
+                .value_name("EASING")
+                .min_values(1)
+                .default_value("linear")
+                .value_delimiter(";"),
+        )
         .arg(
             Arg::with_name("linear")
                 .short("l")
@@ -136,6 +295,123 @@ fn try_main() -> Result<(), Box<dyn Error>> {
                 .required(false)
                 .conflicts_with_all(&["overlay", "size"]),
         )
+        .arg(
+            Arg::with_name("concentric")
+                .short("k")
+                .long("concentric")
+                .help("Create a concentric gradient, changing color with the distance from the center")
+                .required(false)
+                .conflicts_with_all(&["linear", "overlay"]),
+        )
+        .arg(
+            Arg::with_name("wheel")
+                .short("w")
+                .long("wheel")
+                .help("Create a two-axis wheel, with the gradient around it and the radius setting Lch lightness or chroma")
+                .value_name("CHANNEL")
+                .takes_value(true)
+                .possible_values(&["lightness", "chroma"])
+                .case_insensitive(true)
+                .conflicts_with_all(&["linear", "concentric", "overlay"]),
+        )
+        .arg(
+            Arg::with_name("wheel range")
+                .long("wheel-range")
+                .help("Values of the wheel channel at the inner radius and the edge, defaults to 90,30 for lightness and 0,80 for chroma")
+                .value_name("INNER,OUTER")
+                .number_of_values(2)
+                .value_delimiter(",")
+                .allow_hyphen_values(true)
+                .requires("wheel"),
+        )
+        .arg(
+            Arg::with_name("mask")
+                .long("mask")
+                .help("Draw a gamut mask over radial gradients and wheels, made of shapes delimited by `;`. Shapes are `circle(angle radius, size)` or `polygon(angle radius, angle radius, ...)` with angles in degrees and radii from 0.0 at the center to 1.0 at the edge. Stepped gradients only print colors inside the mask")
+                .value_name("SHAPES")
+                .min_values(1)
+                .value_delimiter(";")
+                .conflicts_with_all(&["linear", "concentric"]),
+        )
+        .arg(
+            Arg::with_name("mask style")
+                .long("mask-style")
+                .help("What happens to pixels outside of the gamut mask")
+                .takes_value(true)
+                .possible_values(&["dim", "desaturate", "transparent"])
+                .case_insensitive(true)
+                .default_value("dim"),
+        )
+        .arg(
+            Arg::with_name("mask outline")
+                .long("mask-outline")
+                .help("Color of the gamut mask outline in R,G,B")
+                .value_name("COLOR")
+                .takes_value(true)
+                .default_value("255,255,255"),
+        )
+        .arg(
+            Arg::with_name("mask stroke")
+                .long("mask-stroke")
+                .help("Width of the gamut mask outline in pixels, 0 for none")
+                .value_name("WIDTH")
+                .takes_value(true)
+                .default_value("2"),
+        )
+        .arg(
+            Arg::with_name("slice")
+                .long("slice")
+                .help("Draw the sRGB gamut at a fixed Lch lightness, with hue around the disc and chroma out from the center")
+                .value_name("LIGHTNESS")
+                .takes_value(true)
+                .conflicts_with_all(&["linear", "concentric", "wheel", "overlay", "harmony", "harmony sheet", "palette only"]),
+        )
+        .arg(
+            Arg::with_name("slice chroma")
+                .long("slice-chroma")
+                .help("Lch chroma at the edge of a slice")
+                .value_name("CHROMA")
+                .takes_value(true)
+                .default_value("135"),
+        )
+        .arg(
+            Arg::with_name("slice outside")
+                .long("slice-outside")
+                .help("Leave colors of a slice outside of sRGB transparent, or map them into gamut and mark them with stripes")
+                .takes_value(true)
+                .possible_values(&["transparent", "mark"])
+                .case_insensitive(true)
+                .default_value("transparent"),
+        )
+        .arg(
+            Arg::with_name("simulate")
+                .long("simulate")
+                .help("Simulate a color vision deficiency in rendered images")
+                .value_name("DEFICIENCY")
+                .takes_value(true)
+                .possible_values(&["protan", "deutan", "tritan", "achromat", "protanopia", "deuteranopia", "tritanopia", "achromatopsia", "protanomaly", "deuteranomaly", "tritanomaly", "achromatomaly"])
+                .case_insensitive(true),
+        )
+        .arg(
+            Arg::with_name("severity")
+                .long("severity")
+                .help("Severity of the simulated deficiency from 0.0 to 1.0, lower values are the anomalous forms")
+                .takes_value(true)
+                .default_value("1.0"),
+        )
+        .arg(
+            Arg::with_name("simulate sheet")
+                .long("simulate-sheet")
+                .help("Create a sheet of the stepped gradient with normal color vision and each simulated deficiency, one row of swatches each")
+                .conflicts_with_all(&["concentric", "wheel", "slice", "harmony sheet", "palette only"]),
+        )
+        .arg(
+            Arg::with_name("rings")
+                .long("rings")
+                .help("Number of rings in a stepped wheel")
+                .takes_value(true)
+                .default_value("5"),
+        )
         .arg(
             Arg::with_name("swatch size")
                 .long("ss")
@@ -153,121 +429,212 @@ fn try_main() -> Result<(), Box<dyn Error>> {
             .long("print")
             .help("Print colors produced by stepped gradients")
         )
+        .arg(
+            Arg::with_name("palette")
+                .long("palette")
+                .help("Save the colors produced by stepped gradients as a palette, `.gpl` (GIMP), `.ase` (Adobe Swatch Exchange) or `.aco` (Photoshop)")
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("palette name")
+                .long("palette-name")
+                .help("Name of the saved palette, defaults to the file name. Sets the group name of `.ase` palettes")
+                .value_name("NAME")
+                .takes_value(true)
+                .requires("palette"),
+        )
+        .arg(
+            Arg::with_name("palette columns")
+                .long("palette-columns")
+                .help("Number of columns of a saved GIMP palette, defaults to a single row")
+                .value_name("COLUMNS")
+                .takes_value(true)
+                .requires("palette"),
+        )
+        .arg(
+            Arg::with_name("analyze")
+                .long("analyze")
+                .help("Report each stepped color in sRGB, Lab and Lch with its ΔE76, ΔE2000 and Oklab distance to the previous step, as a table or JSON")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .possible_values(&["table", "json"])
+                .case_insensitive(true),
+        )
+        .arg(
+            Arg::with_name("analyze file")
+                .long("analyze-file")
+                .help("Write the step analysis to a file instead of stdout")
+                .value_name("FILE")
+                .takes_value(true)
+                .requires("analyze"),
+        )
+        .arg(
+            Arg::with_name("contrast")
+                .long("contrast")
+                .help("Report the WCAG 2.1 contrast ratio and APCA Lc of every pair of stepped colors as a table, JSON or a matrix image")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .possible_values(&["table", "json", "image"])
+                .case_insensitive(true),
+        )
+        .arg(
+            Arg::with_name("contrast target")
+                .long("contrast-target")
+                .help("Mark each pair as passing or failing WCAG AA (4.5:1), AAA (7:1), or an APCA Lc value such as 60")
+                .value_name("TARGET")
+                .takes_value(true)
+                .default_value("aa"),
+        )
+        .arg(
+            Arg::with_name("contrast file")
+                .long("contrast-file")
+                .help("Write the contrast report to a file instead of stdout, or name the matrix image")
+                .value_name("FILE")
+                .takes_value(true)
+                .requires("contrast"),
+        )
         .arg(
             Arg::with_name("no file")
             .long("no-file")
-            .help("Don't output file, for use with printing stepped gradient colors")
+            .help("Don't output file, for use with printing or saving a palette of stepped gradient colors")
         )
         .get_matches();
 
-    let mut grad_vec = Vec::with_capacity(32);
-
-    if m.occurrences_of("colors") > 0 {
-        if let Some(colors) = m.values_of("colors") {
-            for color in colors {
-                let c = color.split(",").collect::<Vec<_>>();
-                let r: u8 = c[0].parse().unwrap_or_else(|_| {
-                    panic!("Could not parse Red in {}, value should be 0-255", color)
-                });
-                let g: u8 = c[1].parse().unwrap_or_else(|_| {
-                    panic!("Could not parse Green in {}, value should be 0-255", color)
-                });
-                let b: u8 = c[2].parse().unwrap_or_else(|_| {
-                    panic!("Could not parse Blue in {}, value should be 0-255", color)
-                });
-                grad_vec.push(Lch::from(
-                    Srgb::new(r, g, b).into_format::<f32>().into_linear(),
-                ));
+    let seed = m.value_of("seed").unwrap().parse::<u64>()?;
+
+    // Colors are collected with their command-line index so that colors from
+    // different flags keep the order they were written in.
+    let mut indexed_colors: Vec<(usize, ColorStop)> = Vec::with_capacity(32);
+
+    let notations = [
+        ("colors", "--colors", Notation::Rgb),
+        ("decimal colors", "--dec", Notation::Decimal),
+        ("hex colors", "--hex", Notation::Hex),
+        ("hsv colors", "--hsv", Notation::Hsv),
+        ("lch colors", "--lch", Notation::Lch),
+    ];
+    for &(name, flag, notation) in notations.iter() {
+        if m.occurrences_of(name) > 0 {
+            if let Some(colors) = m.values_of(name) {
+                let parsed = parse_colors(flag, notation, colors)?;
+                indexed_colors.extend(indices_of(&m, name).zip(parsed));
             }
         }
     }
 
-    if m.occurrences_of("decimal colors") > 0 {
-        if let Some(colors) = m.values_of("decimal colors") {
-            for color in colors {
-                let c = color.split(",").collect::<Vec<_>>();
-                let r: f32 = c[0].parse().unwrap_or_else(|_| {
-                    panic!("Could not parse Red in {}, value should be 0.0-1.0", color)
-                });
-                let g: f32 = c[1].parse().unwrap_or_else(|_| {
-                    panic!(
-                        "Could not parse Green in {}, value should be 0.0-1.0",
-                        color
-                    )
-                });
-                let b: f32 = c[2].parse().unwrap_or_else(|_| {
-                    panic!("Could not parse Blue in {}, value should be 0.0-1.0", color)
-                });
-                grad_vec.push(Lch::from(Srgb::new(r, g, b).into_linear()));
-            }
-        }
+    // Palette files aren't limited to 32 colors like the color flags.
+    if let Some(path) = m.value_of("palette file") {
+        let index = m.index_of("palette file").unwrap_or(0);
+        let parsed = read_palette(Path::new(path))?;
+        indexed_colors.extend(parsed.into_iter().map(|c| (index, c)));
     }
 
-    // if m.occurrences_of("hex colors") > 0 {
-    //     if let Some(colors) = m.values_of("hex colors") {
-    //         for color in colors {
-    //             let rgb = Rgb::<encoding::Srgb, u8>::from_str(color).unwrap();
-    //             grad_vec.push(Lch::from(rgb.into_format::<f32>().into_linear()));
-    //         }
-    //     }
-    // }
-
-    if m.occurrences_of("hsv colors") > 0 {
-        if let Some(colors) = m.values_of("hsv colors") {
-            for color in colors {
-                let c = color.split(",").collect::<Vec<_>>();
-                let h: f32 = c[0].parse().unwrap_or_else(|_| {
-                    panic!("Could not parse Hue in {}, value should be 0-360", color)
-                });
-                let s: f32 = c[1].parse().unwrap_or_else(|_| {
-                    panic!(
-                        "Could not parse Saturation in {}, value should be 0-100",
-                        color
-                    )
-                });
-                let v: f32 = c[2].parse().unwrap_or_else(|_| {
-                    panic!("Could not parse Value in {}, value should be 0-100", color)
-                });
-                grad_vec.push(Lch::from(Hsv::new(h, s / 100.0, v / 100.0)));
-            }
-        }
+    if let Some(path) = m.value_of("extract") {
+        let index = m.index_of("extract").unwrap_or(0);
+        let count = m.value_of("extract count").unwrap().parse::<usize>()?;
+        let method = m
+            .value_of("extract method")
+            .unwrap()
+            .parse::<ExtractMethod>()?;
+        let sort = m.value_of("extract sort").unwrap().parse::<ExtractSort>()?;
+        let extracted = extract_colors(Path::new(path), count, method, sort, seed)?;
+        indexed_colors.extend(extracted.into_iter().map(|c| (index, c)));
     }
 
-    if m.occurrences_of("lch colors") > 0 {
-        if let Some(colors) = m.values_of("lch colors") {
-            for color in colors {
-                let c = color.split(",").collect::<Vec<_>>();
-                let l: f32 = c[0].parse().unwrap_or_else(|_| {
-                    panic!(
-                        "Could not parse Lightness in {}, value should be 0-100",
-                        color
-                    )
-                });
-                let chroma: f32 = c[1].parse().unwrap_or_else(|_| {
-                    panic!("Could not parse Chroma in {}, value should be 0-100", color)
-                });
-                let hue: f32 = c[2].parse().unwrap_or_else(|_| {
-                    panic!("Could not parse Hue in {}, value should be 0-360", color)
-                });
-                grad_vec.push(Lch::new(l, chroma, hue));
-            }
-        }
+    indexed_colors.sort_by_key(|&(index, _)| index);
+    let mut stops: Vec<ColorStop> = indexed_colors.into_iter().map(|(_, c)| c).collect();
+    // Slices don't use the input colors.
+    if stops.is_empty() && !m.is_present("slice") {
+        return Err(
+            "No colors given, use one of the color flags such as -c, --palette-file or --extract"
+                .into(),
+        );
+    }
+
+    if let Some(harmony) = m.value_of("harmony") {
+        let harmony = harmony.parse::<Harmony>()?;
+        stops = harmony
+            .colors(stops[0].color)
+            .into_iter()
+            .map(|color| ColorStop {
+                color,
+                position: None,
+            })
+            .collect();
     }
 
+    // Only the angular radial gradient wraps around to the first color.
     let linear;
     if m.is_present("linear") {
         linear = true;
+    } else if m.is_present("concentric")
+        || m.is_present("palette only")
+        || m.is_present("harmony sheet")
+        || m.is_present("simulate sheet")
+        || m.is_present("slice")
+    {
+        linear = false;
     } else {
         linear = false;
-        grad_vec.push(grad_vec[0]);
+        wrap_stops(&mut stops);
     }
 
-    let output_file;
-    if m.is_present("output") {
-        output_file = Some(PathBuf::from(m.value_of("output").unwrap()));
-    } else {
-        output_file = None;
-    }
+    let grad_vec: Vec<Lcha<D65>> = stops.iter().map(|s| s.color).collect();
+    let positions = resolve_positions(&stops.iter().map(|s| s.position).collect::<Vec<_>>())?;
+
+    let hue = m
+        .values_of("hue")
+        .unwrap()
+        .map(|h| h.parse::<HueMethod>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let easing = m
+        .values_of("easing")
+        .unwrap()
+        .map(|e| e.parse::<Easing>())
+        .collect::<Result<Vec<_>, _>>()?;
+    // Radial gradients have one more segment, wrapping from the last color
+    // back to the first, unless the last color is at 1.0.
+    let segments = grad_vec.len().saturating_sub(1);
+    check_segments("hue interpolation method", hue.len(), segments)?;
+    check_segments("easing", easing.len(), segments)?;
+
+    let output_file = m.value_of("output").map(PathBuf::from);
+
+    let format = match (m.value_of("format"), &output_file) {
+        (Some(format), _) => format.parse::<OutputFormat>()?,
+        (None, Some(path)) if path.extension().is_some() => OutputFormat::from_path(path)
+            .ok_or_else(|| {
+                format!(
+                    "Could not determine the image format of {}, use --format to set it",
+                    path.display()
+                )
+            })?,
+        (None, _) => OutputFormat::Png,
+    };
+
+    let analyze = match m.value_of("analyze") {
+        Some(format) => Some(format.parse::<AnalysisFormat>()?),
+        None => None,
+    };
+    let analyze_file = m.value_of("analyze file").map(PathBuf::from);
+
+    let contrast = match m.value_of("contrast") {
+        Some(format) => Some(format.parse::<ContrastFormat>()?),
+        None => None,
+    };
+    let contrast_target = m
+        .value_of("contrast target")
+        .unwrap()
+        .parse::<ContrastTarget>()?;
+    let contrast_file = m.value_of("contrast file").map(PathBuf::from);
+
+    let palette_file = m.value_of("palette").map(PathBuf::from);
+    let palette_name = m.value_of("palette name").map(String::from);
+    let palette_columns = match m.value_of("palette columns") {
+        Some(columns) => Some(columns.parse::<usize>()?),
+        None => None,
+    };
 
     let mut overlay = LinSrgba::from(
         Srgb::new(120u8, 120, 120)
@@ -276,17 +643,8 @@ fn try_main() -> Result<(), Box<dyn Error>> {
     );
     if m.is_present("overlay") {
         if let Some(color) = m.value_of("overlay") {
-            let c = color.split(",").collect::<Vec<_>>();
-            let r: u8 = c[0].parse().unwrap_or_else(|_| {
-                panic!("Could not parse Red in {}, value should be 0-255", color)
-            });
-            let g: u8 = c[1].parse().unwrap_or_else(|_| {
-                panic!("Could not parse Green in {}, value should be 0-255", color)
-            });
-            let b: u8 = c[2].parse().unwrap_or_else(|_| {
-                panic!("Could not parse Blue in {}, value should be 0-255", color)
-            });
-            overlay = LinSrgba::from(Srgb::new(r, g, b).into_format::<f32>().into_linear());
+            let parsed = parse_colors("--overlay", Notation::Rgb, Some(color))?;
+            overlay = LinSrgba::from(parsed[0].color);
         }
     }
 
@@ -327,44 +685,151 @@ fn try_main() -> Result<(), Box<dyn Error>> {
     let angle_offset = core::f32::consts::FRAC_PI_2;
     let overlay_factor = 0.9;
     let size = m.value_of("size").unwrap().parse::<u32>()?;
+    let space = m.value_of("space").unwrap().parse::<Space>()?;
+    let depth = m.value_of("depth").unwrap().parse::<u8>()?;
+    let dither = m.value_of("dither").unwrap().parse::<DitherMethod>()?;
+    let spacing = m.value_of("spacing").unwrap().parse::<StepSpacing>()?;
+    let gamut = m.value_of("gamut").unwrap().parse::<GamutMethod>()?;
+    let gamut_report = m.is_present("gamut report");
     let steps = m.value_of("steps").unwrap().parse::<usize>()?;
-
-    let print_grad;
-    if m.is_present("print") {
-        print_grad = true;
-    } else {
-        print_grad = false;
+    if steps == 0 {
+        return Err("Steps must be at least 1, found 0".into());
     }
-    let no_file;
-    if m.is_present("no file") {
-        no_file = true;
-    } else {
-        no_file = false;
+    let svg_tolerance = m.value_of("svg tolerance").unwrap().parse::<f32>()?;
+    let rings = m.value_of("rings").unwrap().parse::<usize>()?;
+    let severity = m.value_of("severity").unwrap().parse::<f32>()?;
+    if !(0.0..=1.0).contains(&severity) {
+        return Err(format!("Severity must be from 0.0 to 1.0, found {}", severity).into());
+    }
+    let simulate = match m.value_of("simulate") {
+        Some(deficiency) => Some(Simulation {
+            deficiency: deficiency.parse::<Deficiency>()?,
+            severity,
+        }),
+        None => None,
+    };
+    let slice_lightness = match m.value_of("slice") {
+        Some(lightness) => lightness.parse::<f32>()?,
+        None => 50.0,
+    };
+    if !(0.0..=100.0).contains(&slice_lightness) {
+        return Err(format!(
+            "Slice lightness must be from 0 to 100, found {}",
+            slice_lightness
+        )
+        .into());
     }
+    let slice_chroma = m.value_of("slice chroma").unwrap().parse::<f32>()?;
+    let slice_outside = m
+        .value_of("slice outside")
+        .unwrap()
+        .parse::<SliceOutside>()?;
+
+    let mask = match m.values_of("mask") {
+        Some(shapes) => Some(GamutMask {
+            shapes: shapes
+                .map(|s| s.parse::<MaskShape>())
+                .collect::<Result<Vec<_>, _>>()?,
+            style: m.value_of("mask style").unwrap().parse::<MaskStyle>()?,
+            outline: {
+                let parsed =
+                    parse_colors("--mask-outline", Notation::Rgb, m.value_of("mask outline"))?;
+                Srgba::from_linear(LinSrgba::from(parsed[0].color))
+            },
+            stroke: m.value_of("mask stroke").unwrap().parse::<f32>()?,
+        }),
+        None => None,
+    };
+
+    let wheel_channel = match m.value_of("wheel") {
+        Some(channel) => channel.parse::<WheelChannel>()?,
+        None => WheelChannel::Lightness,
+    };
+    let wheel_range = match m.values_of("wheel range") {
+        Some(values) => {
+            let values = values
+                .map(|v| v.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()?;
+            (values[0], values[1])
+        }
+        None => wheel_channel.default_range(),
+    };
+
+    let print_grad = m.is_present("print");
+    let no_file = m.is_present("no file");
 
     let config = Config {
+        analyze,
+        analyze_file,
         angle_offset,
+        contrast,
+        contrast_file,
+        contrast_target,
+        depth,
+        dither,
+        easing,
         grad_vec,
+        hue,
         linear,
+        mask,
         radius_inner,
+        format,
+        gamut,
+        gamut_report,
         output_file,
+        palette_columns,
+        palette_file,
+        palette_name,
+        positions,
         overlay,
         overlay_factor,
         no_file,
         print_grad,
+        rings,
+        seed,
+        severity,
+        simulate,
         size,
+        slice_chroma,
+        slice_lightness,
+        slice_outside,
+        space,
+        spacing,
         steps,
+        svg_tolerance,
         swatch_size,
+        wheel_channel,
+        wheel_range,
     };
 
     let program_type;
 
-    if config.linear {
+    if m.is_present("palette only") {
+        program_type = Work::PaletteOnly;
+    } else if m.is_present("harmony sheet") {
+        program_type = Work::HarmonySheet;
+    } else if m.is_present("simulate sheet") {
+        program_type = Work::SimulationSheet;
+    } else if m.is_present("slice") {
+        program_type = Work::HclSlice;
+    } else if config.linear {
         if m.occurrences_of("steps") > 0 {
             program_type = Work::LinGradStep;
         } else {
             program_type = Work::LinGradCont;
         }
+    } else if m.is_present("wheel") {
+        if m.occurrences_of("steps") > 0 {
+            program_type = Work::WheelStep;
+        } else {
+            program_type = Work::WheelCont;
+        }
+    } else if m.is_present("concentric") {
+        if m.occurrences_of("steps") > 0 {
+            program_type = Work::ConGradStep;
+        } else {
+            program_type = Work::ConGradCont;
+        }
     } else {
         if m.occurrences_of("steps") > 0 {
             program_type = Work::RadGradStep;
@@ -375,13 +840,94 @@ fn try_main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    let stepped = matches!(
+        program_type,
+        Work::ConGradStep
+            | Work::LinGradStep
+            | Work::PaletteOnly
+            | Work::RadGradStep
+            | Work::WheelStep
+    );
+    if config.contrast.is_some() && !stepped {
+        return Err(
+            "The contrast report needs stepped colors, use --steps or --palette-only".into(),
+        );
+    }
+    if config.analyze.is_some() && !stepped {
+        return Err("The step analysis needs stepped colors, use --steps or --palette-only".into());
+    }
+    if config.format == OutputFormat::Svg && !config.no_file {
+        if !matches!(
+            program_type,
+            Work::LinGradCont | Work::LinGradStep | Work::PaletteOnly | Work::RadGradStep
+        ) {
+            return Err(
+                "SVG output is only supported for linear and stepped radial gradients".into(),
+            );
+        }
+        if config.mask.is_some() {
+            return Err("Gamut masks can't be drawn in SVG output".into());
+        }
+    }
+    if config.depth == 16 {
+        if !matches!(
+            program_type,
+            Work::LinGradCont | Work::RadGradCont | Work::RadGradContOverlay
+        ) {
+            return Err(
+                "16-bit output is only supported for continuous linear and radial gradients".into(),
+            );
+        }
+        if config.format != OutputFormat::Png {
+            return Err(format!(
+                "16-bit output is only supported for PNG, not {}",
+                config.format
+            )
+            .into());
+        }
+        if config.dither != DitherMethod::None {
+            return Err("Dithering only applies to 8-bit output".into());
+        }
+    }
+
     match program_type {
+        Work::ConGradCont => concentric_gradient_continuous(config)?,
+        Work::ConGradStep => concentric_gradient_stepped(config)?,
+        Work::HarmonySheet => harmony_sheet(config)?,
+        Work::HclSlice => hcl_slice(config)?,
         Work::LinGradCont => linear_gradient_continuous(config)?,
         Work::LinGradStep => linear_gradient_stepped(config)?,
+        Work::PaletteOnly => palette_only(config)?,
         Work::RadGradCont => radial_gradient_continuous(config)?,
         Work::RadGradContOverlay => radial_gradient_with_overlay(config)?,
         Work::RadGradStep => radial_gradient_stepped(config)?,
+        Work::SimulationSheet => simulation_sheet(config)?,
+        Work::WheelCont => wheel_gradient_continuous(config)?,
+        Work::WheelStep => wheel_gradient_stepped(config)?,
     }
 
     Ok(())
 }
+
+/// Checks that a per-segment option has one value for the whole gradient or
+/// one value for each segment.
+fn check_segments(name: &str, found: usize, segments: usize) -> Result<(), String> {
+    if found == 1 || found == segments {
+        Ok(())
+    } else {
+        Err(format!(
+            "Expected 1 {} or one for each of the {} segments, found {}",
+            name, segments, found
+        ))
+    }
+}
+
+/// Returns the command-line index of each value of `name`. Default values
+/// share the index of the flag itself.
+fn indices_of<'a>(m: &'a ArgMatches, name: &str) -> Box<dyn Iterator<Item = usize> + 'a> {
+    let flag_index = m.index_of(name).unwrap_or(0);
+    match m.indices_of(name) {
+        Some(indices) => Box::new(indices.chain(std::iter::repeat(flag_index))),
+        None => Box::new(std::iter::repeat(flag_index)),
+    }
+}